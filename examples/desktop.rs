@@ -12,17 +12,13 @@ struct TodoStore {
 
 impl TodoStore {
     fn add_todo(&mut self) {
-        self.tasks.push(self.value());
-        self.value.set(String::new());
+        self.push_tasks(self.value());
+        self.set_value(String::new());
     }
 
     fn pop_todo(&mut self) {
         self.tasks.pop();
     }
-
-    fn update_value(&mut self, s: String) {
-        self.value.set(s);
-    }
 }
 
 fn app() -> Element {
@@ -30,7 +26,7 @@ fn app() -> Element {
 
     rsx!(
         input {
-            oninput: move |e| store.update_value(e.data().value()),
+            oninput: move |e| store.set_value(e.data().value()),
             onkeydown: move |e| if e.data().key() == Key::Enter { store.add_todo() },
             value: "{store.value}"
         }
@@ -43,7 +39,7 @@ fn app() -> Element {
             "Pop Task"
         }
         ul {
-            for (idx, task) in store.tasks().iter().enumerate() {
+            for (idx, task) in store.tasks_ref().iter().enumerate() {
                 li {
                     key: "{idx}",
                     "{task}"
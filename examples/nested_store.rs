@@ -1,6 +1,6 @@
 use {
     dioxus::prelude::*,
-    modx::{props, store},
+    modx::{store, StoreVec},
 };
 
 fn main() {
@@ -8,14 +8,28 @@ fn main() {
 }
 
 // CoutnerS
-#[props(counters)]
 #[store]
 struct CountersStore {
-    counters: Vec<CounterStore>,
+    #[modx(nested)]
+    total:    CounterStore,
+    #[modx(nested)]
+    counters: StoreVec<CounterStore>,
+}
+
+impl CountersStore {
+    fn add_counter(&mut self) {
+        self.counters.push(());
+        self.total.inc();
+    }
+
+    fn remove_counter(&mut self, key: usize) {
+        if self.counters.remove(key) {
+            self.total.dec();
+        }
+    }
 }
 
 // Counter
-#[derive(Debug, PartialEq, Eq)]
 #[store]
 struct CounterStore {
     count: i64,
@@ -31,38 +45,38 @@ impl CounterStore {
     }
 }
 
-#[derive(PartialEq, Props, Clone)]
-struct StoreProps {
-    store: CounterStore,
-}
-
-fn my_button(mut props: StoreProps) -> Element {
+fn my_button(mut store: CounterStore) -> Element {
     rsx! {
         button {
-            onclick: move |_| props.store.inc(),
+            onclick: move |_| store.inc(),
             "+1"
         }
         button {
-            onclick: move |_| props.store.dec(),
+            onclick: move |_| store.dec(),
             "-1"
         }
     }
 }
 
 fn app() -> Element {
-    let a = CounterStore::new();
-    let b = CounterStore::new();
-    let store = CountersStore::new(CountersStoreProps {
-        counters: vec![a, b],
-    });
+    let mut store = CountersStore::new();
 
     rsx! {
-        my_button { store: store.counters()[0] }
-        br{}
-        my_button { store: store.counters()[1] }
-        br{}
-        "{a.count}"
-        br{}
-        "{b.count}"
+        "Number of counters: {store.total.count}"
+        for (key, counter) in store.counters.iter_keyed() {
+            div {
+                key: "{key}",
+                my_button { store: counter }
+                "{counter.count}"
+                button {
+                    onclick: move |_| store.remove_counter(key),
+                    "Remove"
+                }
+            }
+        }
+        button {
+            onclick: move |_| store.add_counter(),
+            "Add a counter"
+        }
     }
 }
@@ -1,6 +1,6 @@
 use {
     dioxus::prelude::*,
-    modx::{props, store},
+    modx::{props, store, StoreVec},
 };
 
 fn main() {
@@ -9,25 +9,15 @@ fn main() {
 
 #[store]
 struct Shop {
-    input:        String,
-    items_string: Vec<String>,
+    input: String,
+    #[modx(nested)]
+    items: StoreVec<Item>,
 }
 
 impl Shop {
     fn add_item(&mut self) {
-        self.items_string.push(self.input());
-        self.input.set(String::new());
-    }
-
-    fn get_items(&self) -> Vec<Item> {
-        self.items_string()
-            .iter()
-            .map(|item| Item::new(ItemProps { name: item.clone() }))
-            .collect::<Vec<_>>()
-    }
-
-    fn on_input(&mut self, s: String) {
-        self.input.set(s);
+        self.items().push(ItemProps { name: self.input() });
+        self.set_input(String::new());
     }
 }
 
@@ -58,23 +48,23 @@ fn app() -> Element {
         ul {
             list_style: "none",
             padding_left: "0px",
-            for (idx, &mut item) in shop.get_items().iter_mut().enumerate() {
+            for (key, mut item) in shop.items().iter_keyed() {
                 li {
-                    key: "{idx}",
+                    key: "{key}",
                     padding: "10px",
                     margin_top: "10px",
                     background: "#eee",
                     "{item.name}"
                     div {
                         "{item.number_to_buy}"
-                        button { onclick: move |_| item.clone().inc(), "+1" }
-                        button { onclick: move |_| item.clone().dec(), "-1" }
+                        button { onclick: move |_| item.inc(), "+1" }
+                        button { onclick: move |_| item.dec(), "-1" }
                     }
                 }
             }
         }
         input {
-            oninput: move |e| shop.on_input(e.data().value()),
+            oninput: move |e| shop.set_input(e.data().value()),
             onkeydown: move |e| if e.data().key() == Key::Enter { shop.add_item() },
             value: "{shop.input}"
         }
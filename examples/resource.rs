@@ -23,7 +23,7 @@ struct ApiResponse {
 
 impl CatStore {
     fn clear(&mut self) {
-        self.fetch_cat.clear();
+        self.clear_fetch_cat();
         self.number_of_cats.set(0);
     }
 
@@ -57,7 +57,7 @@ fn app() -> Element {
             None => rsx!( "No cat for now." ),
         }
         button {
-            onclick: move |_| store.fetch_cat.restart(),
+            onclick: move |_| store.reload_fetch_cat(),
             "Get a new cat!"
         }
         button {
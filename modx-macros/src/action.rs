@@ -0,0 +1,252 @@
+use {
+    crate::options::parse_concurrency,
+    proc_macro2::{Span, TokenStream},
+    quote::{format_ident, quote, quote_spanned},
+    syn::{
+        meta::ParseNestedMeta,
+        spanned::Spanned,
+        FnArg,
+        Ident,
+        ImplItemFn,
+        LitInt,
+        Path,
+        ReturnType,
+        Type,
+    },
+};
+
+/// Options of an action, given with `#[modx::action(...)]`.
+#[derive(Default)]
+pub struct ActionOptions {
+    debounce_ms: Option<LitInt>,
+    throttle_ms: Option<LitInt>,
+    /// The variant of `modx::Concurrency`, `Parallel` by default.
+    concurrency: Option<Ident>,
+    /// Restore the store if the method returns an error. The calls are always queued.
+    optimistic: Option<Span>,
+    timer: Option<Path>,
+}
+
+impl ActionOptions {
+    /// Parse one option of the `action` attribute.
+    pub fn parse(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("debounce_ms") {
+            self.debounce_ms = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("throttle_ms") {
+            self.throttle_ms = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("concurrency") {
+            self.concurrency = Some(parse_concurrency(meta)?);
+        } else if meta.path.is_ident("optimistic") {
+            self.optimistic = Some(meta.path.span());
+        } else if meta.path.is_ident("timer") {
+            self.timer = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("unknown action option"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Implement an action: the method keeps its body under a hidden name, and the method itself
+/// only schedules a call to it.
+///
+/// If the last argument of the method is a `modx::CancellationToken`, it is given by the action
+/// instead of the caller.
+pub fn impl_action(options: ActionOptions, mut method: ImplItemFn) -> syn::Result<TokenStream> {
+    // An optimistic call changes the store at once, so it can't wait for a delay, and its rest is
+    // always queued, so that a failed call only undoes the calls made after it
+    if options.optimistic.is_some() &&
+        let Some(span) = options
+            .debounce_ms
+            .as_ref()
+            .or(options.throttle_ms.as_ref())
+            .map(LitInt::span)
+            .or_else(|| options.concurrency.as_ref().map(Ident::span))
+    {
+        return Err(syn::Error::new(
+            span,
+            "an optimistic action runs at once and queues its calls, so it can't have \
+             `debounce_ms`, `throttle_ms` or `concurrency`",
+        ));
+    }
+
+    let schedule = match (options.debounce_ms, options.throttle_ms) {
+        (Some(debounce_ms), None) => Some((quote! { debounce }, debounce_ms)),
+        (None, Some(throttle_ms)) => Some((quote! { throttle }, throttle_ms)),
+        (Some(_), Some(throttle_ms)) => {
+            return Err(syn::Error::new_spanned(
+                throttle_ms,
+                "an action can't be both debounced and throttled",
+            ));
+        },
+        (None, None) if options.concurrency.is_some() || options.optimistic.is_some() => None,
+        (None, None) => {
+            return Err(syn::Error::new(
+                method.sig.ident.span(),
+                "an action needs `debounce_ms`, `throttle_ms`, `concurrency` or `optimistic`",
+            ));
+        },
+    };
+    let concurrency = options
+        .concurrency
+        .unwrap_or_else(|| Ident::new("Parallel", method.sig.ident.span()));
+    let timer = options
+        .timer
+        .map_or_else(|| quote! { ::modx::ThreadTimer }, |timer| quote! { #timer });
+
+    match (&method.sig.output, options.optimistic) {
+        (ReturnType::Type(_, ty), None) => {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "an action can't return a value, since it may run later",
+            ));
+        },
+        (ReturnType::Default, Some(optimistic)) => {
+            return Err(syn::Error::new(
+                optimistic,
+                "an optimistic action needs to return a `Result`, to know if it failed",
+            ));
+        },
+        _ => {},
+    }
+
+    let Some(FnArg::Receiver(receiver)) = method.sig.inputs.first() else {
+        return Err(syn::Error::new(
+            method.sig.span(),
+            "an action needs to be a method of a store",
+        ));
+    };
+    let store_mutability = receiver.mutability;
+
+    // The value of an optimistic action is only used to know if the store is restored
+    let mut signature = method.sig.clone();
+    signature.output = ReturnType::Default;
+    let token = method
+        .sig
+        .inputs
+        .last()
+        .is_some_and(is_cancellation_token)
+        .then(|| {
+            signature.inputs.pop();
+            quote! { token }
+        });
+
+    // The arguments are given to the hidden method, so they need a name
+    let args = (1..signature.inputs.len())
+        .map(|index| format_ident!("arg_{index}"))
+        .collect::<Vec<_>>();
+    for (input, arg) in signature.inputs.iter_mut().skip(1).zip(&args) {
+        if let FnArg::Typed(pat_type) = input {
+            *pat_type.pat = syn::parse_quote! { #arg };
+        }
+    }
+    signature.asyncness = None;
+
+    let name = &method.sig.ident;
+    let hidden_name = format_ident!("_modx_action_{name}");
+    let args = args.iter().map(|arg| quote! { #arg }).chain(token.clone());
+    let call = if method.sig.asyncness.is_some() {
+        quote! { store.#hidden_name(#(#args),*).await }
+    } else {
+        quote! { store.#hidden_name(#(#args),*) }
+    };
+    let token_pat = token
+        .as_ref()
+        .map_or_else(|| quote! { _ }, |token| quote! { #token });
+    let call = quote! {
+        move |#token_pat: ::modx::CancellationToken| async move {
+            let #store_mutability store = store;
+            #call
+        }
+    };
+
+    let schedule = match (&method.sig.output, schedule) {
+        // The value is checked with its own span, so that a value that isn't a `Result` is
+        // reported there
+        (ReturnType::Type(_, ty), _) => {
+            let optimistic = quote_spanned! { ty.span()=> ::modx::optimistic };
+            quote! {
+                thread_local! {
+                    static OPTIMISTIC: ::modx::OptimisticCalls =
+                        const { ::modx::OptimisticCalls::new() };
+                }
+
+                #optimistic(
+                    &OPTIMISTIC,
+                    CALLS.with(::core::clone::Clone::clone),
+                    store,
+                    Self::_modx_restore,
+                    #call,
+                );
+            }
+        },
+        // A debounced or throttled call runs in the task that waits, else it gets its own task
+        (ReturnType::Default, Some((schedule, delay_ms))) => {
+            quote! {
+                thread_local! {
+                    static ACTION: ::modx::ActionState = const { ::modx::ActionState::new() };
+                }
+
+                ::modx::#schedule::<#timer, _, _, _>(
+                    &ACTION,
+                    store,
+                    std::time::Duration::from_millis(#delay_ms),
+                    move || async move {
+                        ::modx::run_concurrently(
+                            CALLS.with(::core::clone::Clone::clone),
+                            store,
+                            ::modx::Concurrency::#concurrency,
+                            #call,
+                        )
+                        .await;
+                    },
+                );
+            }
+        },
+        (ReturnType::Default, None) => {
+            quote! {
+                ::modx::spawn_concurrently(
+                    CALLS.with(::core::clone::Clone::clone),
+                    store,
+                    ::modx::Concurrency::#concurrency,
+                    #call,
+                );
+            }
+        },
+    };
+
+    let attrs = std::mem::take(&mut method.attrs);
+    let vis = std::mem::replace(&mut method.vis, syn::Visibility::Inherited);
+    method.sig.ident = hidden_name;
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #signature {
+            thread_local! {
+                static CALLS: ::modx::Calls = ::modx::Calls::default();
+            }
+
+            let store = <Self as ::core::clone::Clone>::clone(&self);
+            #schedule
+        }
+
+        #[doc(hidden)]
+        #method
+    })
+}
+
+/// If the argument is a `CancellationToken`, with any path.
+fn is_cancellation_token(input: &FnArg) -> bool {
+    let FnArg::Typed(pat_type) = input else {
+        return false;
+    };
+    let Type::Path(type_path) = &*pat_type.ty else {
+        return false;
+    };
+    type_path
+        .path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "CancellationToken")
+}
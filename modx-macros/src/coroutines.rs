@@ -0,0 +1,64 @@
+use {
+    crate::StoreField,
+    proc_macro2::TokenStream,
+    quote::{format_ident, quote, quote_spanned},
+    syn::{spanned::Spanned, Ident},
+};
+
+/// Implement the helpers of a coroutine field, that send it messages or restart it. They have the
+/// write visibility of the field, since the coroutine usually writes the store.
+pub fn impl_coroutine_helpers(struct_name: &Ident, store_field: &StoreField) -> TokenStream {
+    let StoreField {
+        ident,
+        ty,
+        write_vis,
+        ..
+    } = store_field;
+    let send = format_ident!("send_{ident}");
+    let restart = format_ident!("restart_{ident}");
+
+    quote! {
+        impl #struct_name {
+            #write_vis fn #send(&self, message: #ty) {
+                self.#ident.send(message);
+            }
+
+            #write_vis fn #restart(&mut self) {
+                self.#ident.restart();
+            }
+        }
+    }
+}
+
+/// The value of a coroutine field in the literal of the store.
+///
+/// The coroutine needs the store, so this placeholder, that stops right away, is replaced by
+/// [`new_coroutine`] just after the store is created.
+pub fn default_coroutine(store_field: &StoreField) -> TokenStream {
+    let ident = &store_field.ident;
+    quote! {
+        #ident: use_coroutine(|_| std::future::ready(())),
+    }
+}
+
+/// Start the coroutine of a field in `new`, once the rest of the store is created.
+///
+/// The coroutine isn't restarted by the signals it reads, only by `restart_field()`.
+pub fn new_coroutine(store_field: &StoreField) -> TokenStream {
+    let StoreField {
+        ident,
+        resource_options,
+        ..
+    } = store_field;
+
+    let run = match &resource_options.loader {
+        Some(loader) => quote_spanned! { loader.span()=> (#loader)(&mut default_struct, rx) },
+        None => quote_spanned! { ident.span()=> default_struct.#ident(rx) },
+    };
+
+    quote! {
+        default_struct.#ident = use_coroutine(move |rx| {
+            ::modx::untracked(async move { #run.await })
+        });
+    }
+}
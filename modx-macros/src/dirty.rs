@@ -0,0 +1,141 @@
+use {
+    crate::{most_restrictive_write_vis, Constructor, StoreField, TypeOfField},
+    proc_macro2::TokenStream,
+    quote::{format_ident, quote},
+    syn::{parse_quote, Field, Ident, Visibility},
+};
+
+/// If the field remembers the value it had when the store was created. Only the signals and the
+/// props do, since the other fields are loaded, or are stores that track their own fields.
+pub const fn is_tracked(type_of_field: TypeOfField) -> bool {
+    matches!(type_of_field, TypeOfField::Signal | TypeOfField::Props)
+}
+
+/// The hidden field of the store that keeps the initial value of a field.
+///
+/// It's a signal, so that `mark_clean` rerenders the components that read `is_dirty`.
+pub fn initial_field(store_field: &StoreField) -> Option<Field> {
+    if !is_tracked(store_field.type_of_field) {
+        return None;
+    }
+
+    let initial = initial_ident(&store_field.ident);
+    let ty = &store_field.ty;
+    Some(parse_quote! { #initial: Signal<#ty> })
+}
+
+/// The initial value of a field in the literal of the store, when it's created with
+/// `constructor`.
+///
+/// `value` is evaluated separately from the value of the signal, so it must come before it when
+/// the signal takes it, like a prop.
+pub fn initial_value(
+    store_field: &StoreField,
+    constructor: Constructor,
+    value: &TokenStream,
+) -> TokenStream {
+    let initial = initial_ident(&store_field.ident);
+    let signal = constructor.new_signal(value);
+    quote! { #initial: #signal, }
+}
+
+/// Implement the methods that compare the fields to their initial value, and that reset them.
+///
+/// The values are compared only where these methods are used, so that stores with fields that
+/// aren't `PartialEq` can still be declared.
+pub fn impl_dirty(
+    struct_name: &Ident,
+    struct_visibility: &Visibility,
+    all_idents_types: &[StoreField],
+) -> TokenStream {
+    let fields = all_idents_types
+        .iter()
+        .filter(|StoreField { type_of_field, .. }| is_tracked(*type_of_field))
+        .collect::<Vec<_>>();
+
+    if fields.is_empty() {
+        return quote! {};
+    }
+
+    let field_methods = fields.iter().map(
+        |StoreField {
+             ident,
+             ty,
+             read_vis,
+             write_vis,
+             ..
+         }| {
+            let initial = initial_ident(ident);
+            let is_dirty = format_ident!("{ident}_is_dirty");
+            let reset = format_ident!("reset_{ident}");
+            quote! {
+                #read_vis fn #is_dirty(&self) -> bool where for<'a> #ty: PartialEq {
+                    *self.#ident.read() != *self.#initial.read()
+                }
+
+                #write_vis fn #reset(&mut self) {
+                    self.#ident.set(self.#initial.peek().clone());
+                }
+            }
+        },
+    );
+
+    let idents = fields
+        .iter()
+        .map(|StoreField { ident, .. }| ident)
+        .collect::<Vec<_>>();
+    let names = idents.iter().map(ToString::to_string);
+    let initials = idents
+        .iter()
+        .map(|ident| initial_ident(ident))
+        .collect::<Vec<_>>();
+    let is_dirties = idents
+        .iter()
+        .map(|ident| format_ident!("{ident}_is_dirty"))
+        .collect::<Vec<_>>();
+    let bounds = fields
+        .iter()
+        .map(|StoreField { ty, .. }| quote! { for<'a> #ty: PartialEq })
+        .collect::<Vec<_>>();
+    let write_vis = most_restrictive_write_vis(&fields);
+
+    quote! {
+        impl #struct_name {
+            #(#field_methods)*
+
+            /// If a field has a different value than when the store was created, or last marked
+            /// as clean.
+            #struct_visibility fn is_dirty(&self) -> bool where #(#bounds,)* {
+                #(self.#is_dirties())||*
+            }
+
+            /// The names of the fields that have a different value than when the store was
+            /// created, or last marked as clean.
+            #struct_visibility fn dirty_fields(&self) -> Vec<&'static str> where #(#bounds,)* {
+                let mut dirty_fields = Vec::new();
+                #(
+                    if self.#is_dirties() {
+                        dirty_fields.push(#names);
+                    }
+                )*
+                dirty_fields
+            }
+
+            /// Give back to every field the value it had when the store was created, or last
+            /// marked as clean.
+            #write_vis fn reset(&mut self) {
+                #(self.#idents.set(self.#initials.peek().clone());)*
+            }
+
+            /// Use the current value of every field as its initial value, like after the changes
+            /// are saved.
+            #write_vis fn mark_clean(&mut self) {
+                #(self.#initials.set(self.#idents.peek().clone());)*
+            }
+        }
+    }
+}
+
+pub fn initial_ident(ident: &Ident) -> Ident {
+    format_ident!("_modx_initial_{ident}")
+}
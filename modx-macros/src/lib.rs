@@ -0,0 +1,1739 @@
+mod action;
+mod coroutines;
+mod dirty;
+mod mutators;
+mod options;
+mod resources;
+mod snapshot;
+mod streams;
+mod view;
+
+use {
+    action::{impl_action, ActionOptions},
+    coroutines::{default_coroutine, impl_coroutine_helpers, new_coroutine},
+    dirty::{impl_dirty, initial_field, initial_ident, initial_value, is_tracked},
+    mutators::{impl_mutators, impl_setters},
+    options::{FieldOptions, ResourceOptions, StoreOptions},
+    proc_macro::TokenStream as OriginalTokenStream,
+    proc_macro2::TokenStream,
+    quote::{format_ident, quote},
+    resources::{
+        default_resource,
+        impl_refresh_all,
+        impl_resource_helpers,
+        new_resource,
+        pages_field,
+        refresh_field,
+    },
+    snapshot::impl_snapshot,
+    streams::{default_stream, impl_stream_helpers, new_stream, stream_fields},
+    syn::{
+        parse::{Parse, ParseStream},
+        parse_macro_input,
+        parse_quote,
+        parse_quote_spanned,
+        punctuated::Punctuated,
+        spanned::Spanned,
+        DeriveInput,
+        Ident,
+        Token,
+    },
+    view::impl_view,
+};
+
+/// A field given to `props` or `resource`, like `avatar` or `avatar = load_avatar`.
+#[derive(Clone)]
+struct Arg {
+    ident: Ident,
+    /// The async function that loads a resource, called with the store.
+    loader: Option<syn::Expr>,
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let ident = input.parse()?;
+        let loader = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Arg { ident, loader })
+    }
+}
+
+/// The fields given to `props` or `resource`, with the span where they were written.
+struct Args {
+    vars: Vec<Arg>,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        // parses a,b,c, or a,b = c where a,b are Indent and c is an expression
+        let parsed = Punctuated::<Arg, Token![,]>::parse_terminated(input)?;
+
+        let mut vars: Vec<Arg> = vec![];
+        for var in parsed {
+            if vars.iter().any(|other| other.ident == var.ident) {
+                return Err(syn::Error::new_spanned(
+                    var.ident,
+                    "this field is given twice",
+                ));
+            }
+            vars.push(var);
+        }
+
+        Ok(Args { vars })
+    }
+}
+
+impl Args {
+    /// Check that every given field is a field of the struct, and that only resources have a
+    /// loader.
+    fn check(&self, fields: &syn::Fields, marker: &str) -> syn::Result<()> {
+        let mut error: Option<syn::Error> = None;
+        let mut add_error = |new_error: syn::Error| {
+            match &mut error {
+                Some(error) => error.combine(new_error),
+                None => error = Some(new_error),
+            }
+        };
+
+        for Arg { ident, loader } in &self.vars {
+            if !fields
+                .iter()
+                .any(|field| field.ident.as_ref() == Some(ident))
+            {
+                add_error(syn::Error::new_spanned(
+                    ident,
+                    format!("no field named `{ident}`"),
+                ));
+            }
+            if let Some(loader) = loader &&
+                marker != "resource"
+            {
+                add_error(syn::Error::new_spanned(
+                    loader,
+                    "only a resource can be given a loader",
+                ));
+            }
+        }
+
+        error.map_or(Ok(()), Err)
+    }
+
+    /// The given field with the same name as `ident`.
+    fn get(&self, ident: &Ident) -> Option<&Arg> {
+        self.vars.iter().find(|var| var.ident == *ident)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TypeOfField {
+    Signal,
+    Resource,
+    // ServerFuture,
+    Props,
+    Nested,
+    Stream,
+    Coroutine,
+}
+
+/// A field of a store.
+struct StoreField {
+    ident: Ident,
+    /// The type written by the user, before being wrapped in a signal or a resource. It's the type
+    /// of the messages of a coroutine.
+    ty: syn::Type,
+    /// Visibility of the methods that read the field. It's the visibility of the field.
+    read_vis: syn::Visibility,
+    /// Visibility of the methods that write the field. It's the visibility of the field, unless
+    /// the field is `readonly`.
+    write_vis: syn::Visibility,
+    type_of_field: TypeOfField,
+    /// How the field is loaded, if it's a resource or a stream.
+    resource_options: ResourceOptions,
+}
+
+impl StoreField {
+    fn new(
+        ident: &Ident,
+        field: &syn::Field,
+        type_of_field: TypeOfField,
+        options: &FieldOptions,
+    ) -> Self {
+        Self {
+            ident: ident.clone(),
+            ty: field.ty.clone(),
+            read_vis: field.vis.clone(),
+            write_vis: if options.readonly {
+                syn::Visibility::Inherited
+            } else {
+                field.vis.clone()
+            },
+            type_of_field,
+            resource_options: options.resource_options.clone(),
+        }
+    }
+
+    /// If the field is `readonly`, so that it can be read outside of the module of the store but
+    /// not written.
+    const fn readonly(&self) -> bool {
+        matches!(self.write_vis, syn::Visibility::Inherited) &&
+            !matches!(self.read_vis, syn::Visibility::Inherited)
+    }
+}
+
+/// The visibility of a method that writes every field of `fields`, so that it can't write a field
+/// where its setter can't be called, like a `readonly` one outside of the module of the store.
+///
+/// Visibilities restricted to different paths can't be compared, so the methods are private then.
+fn most_restrictive_write_vis(fields: &[&StoreField]) -> syn::Visibility {
+    fn rank(vis: &syn::Visibility) -> u8 {
+        match vis {
+            syn::Visibility::Public(_) => 3,
+            syn::Visibility::Restricted(restricted) if restricted.path.is_ident("crate") => 2,
+            syn::Visibility::Restricted(_) => 1,
+            syn::Visibility::Inherited => 0,
+        }
+    }
+
+    let visibilities = fields
+        .iter()
+        .map(|StoreField { write_vis, .. }| write_vis)
+        .collect::<Vec<_>>();
+    let Some(most_restrictive) = visibilities.iter().copied().min_by_key(|vis| rank(vis)) else {
+        return syn::Visibility::Inherited;
+    };
+    let comparable = visibilities.iter().all(|vis| {
+        rank(vis) != rank(most_restrictive) ||
+            quote!(#vis).to_string() == quote!(#most_restrictive).to_string()
+    });
+
+    if comparable {
+        most_restrictive.clone()
+    } else {
+        syn::Visibility::Inherited
+    }
+}
+
+/// How a store is created.
+#[derive(Clone, Copy)]
+enum Constructor {
+    /// With `new`, which calls hooks.
+    Hook,
+    /// With `new_in`, owned by the scope `owner`.
+    InScope,
+    /// With `new_detached`, owned by the current owner.
+    Detached,
+}
+
+impl Constructor {
+    fn new_signal(self, value: &TokenStream) -> TokenStream {
+        match self {
+            Self::Hook => quote! { use_signal(|| #value) },
+            Self::InScope => quote! { Signal::new_in_scope(#value, owner) },
+            Self::Detached => quote! { Signal::new(#value) },
+        }
+    }
+
+    fn new_nested(self, ty: &syn::Type) -> TokenStream {
+        match self {
+            Self::Hook => quote! { <#ty as ::modx::Nested>::use_nested() },
+            Self::InScope => {
+                quote! { <#ty as ::modx::NestedOutsideHooks>::new_nested(Some(owner)) }
+            },
+            Self::Detached => quote! { <#ty as ::modx::NestedOutsideHooks>::new_nested(None) },
+        }
+    }
+}
+
+/// Declare a struct as a modx store.
+///
+/// ## Usage
+/// ```
+/// #[modx::store]
+/// struct MyStore {
+///     name: String,
+///     age: u8,
+/// }
+/// ```
+///
+/// ## Attributes
+/// By default, this struct will have some implementation. The main one being `new`.
+/// New will ONLY work if all fields have a type that implements the `Default` trait.
+/// If one does not, you will need to use the `#[modx::props]` macro to take some parameters.
+///
+/// ## Example
+///
+/// When every fields implements default.
+/// ```
+/// #[modx::store]
+/// struct MyStore {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// let store = MyStore::new();  // { name: String(""), age: 0 }
+/// ```
+///
+/// When the component takes props
+/// ```
+/// #[modx::props(age)]
+/// #[modx::store]
+/// struct MyStore {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// let store = MyStore::new(MyStoreProps { age: 21 });  // { name: String(""), age: 21 }
+/// ```
+///
+///
+/// ## Accessors
+/// For every field, the store has a getter that clones its value, and two accessors that only
+/// borrow it, which avoids cloning a whole `Vec` just to iterate over it.
+///
+/// ```
+/// #[modx::store]
+/// struct TodoStore {
+///     tasks: Vec<String>,
+/// }
+///
+/// let store = TodoStore::new();
+/// let tasks: Vec<String> = store.tasks();
+/// let first = store.tasks_ref().first().cloned();
+/// let len = store.with_tasks(|tasks| tasks.len());
+/// ```
+///
+/// If some fields have a type that isn't `Clone`, the cloning getters can be disabled with
+/// `#[modx::store(no_clone)]`. This also disables the dirty tracking, which clones the initial
+/// values.
+///
+/// ## Setters
+/// Every field also has setters:
+/// - `set_x(value)` replaces the value.
+/// - `update_x(|value| ...)` modifies the value in place, and returns what the closure returns.
+/// - `replace_x(value)` replaces the value, and returns the previous one.
+///
+/// ```
+/// #[modx::store]
+/// struct TodoStore {
+///     value: String,
+/// }
+///
+/// let mut store = TodoStore::new();
+/// store.set_value(String::from("Buy a cat"));
+/// store.update_value(|value| value.push('!'));
+/// let previous = store.replace_value(String::new());
+/// ```
+///
+/// ## Mutators
+/// Depending on the type of a field, the store also has some mutators for it:
+/// - `bool`: `toggle_x()`
+/// - numbers: `inc_x()` and `dec_x()`
+/// - `Vec<T>`: `push_x(T)`, `remove_x(index)`, `clear_x()` and `retain_x(|&T| bool)`
+/// - `HashMap<K, V>` and `BTreeMap<K, V>`: `insert_x(K, V)` and `remove_x(&K)`
+/// - `Option<T>`: `take_x()` and `replace_x(T)`, which replaces the `replace_x` of the setters
+///
+/// ```
+/// #[modx::store]
+/// struct CounterStore {
+///     count: i64,
+///     visible: bool,
+/// }
+///
+/// let mut store = CounterStore::new();
+/// store.inc_count();
+/// store.toggle_visible();
+/// ```
+///
+/// The type is detected from its name, so these mutators aren't generated for type aliases.
+///
+/// ## Visibility
+/// The generated accessors, setters and mutators have the same visibility as their field, and so
+/// do the fields of the props struct. A private field of a public store stays private.
+///
+/// With `#[modx(readonly)]`, a field can be read with its visibility, but only written from the
+/// module of the store.
+///
+/// ```
+/// #[modx::store]
+/// pub struct CounterStore {
+///     #[modx(readonly)]
+///     pub count: i64,
+/// }
+///
+/// impl CounterStore {
+///     pub fn inc(&mut self) {
+///         self.inc_count();
+///     }
+/// }
+///
+/// // In another module
+/// store.count();      // Ok
+/// store.inc();        // Ok
+/// store.set_count(2); // Error: `set_count` is private
+/// ```
+///
+/// ## Read-only view
+/// `store.view()` creates a `MyStoreView`, where every field is a `ReadOnlySignal`, and a resource
+/// field is the `ReadOnlySignal` of its value. It has the same cloning getters as the store, and
+/// it can be used as the props of a component, so parents can share their state with their
+/// children without giving them write access. Nested stores are not part of the view.
+///
+/// ```
+/// #[modx::store]
+/// struct CounterStore {
+///     count: i64,
+/// }
+///
+/// fn Counter(view: CounterStoreView) -> Element {
+///     rsx! { "{view.count}" }
+/// }
+///
+/// fn app() -> Element {
+///     let store = CounterStore::new();
+///     rsx! { Counter { view: store.view() } }
+/// }
+/// ```
+///
+/// ## With other macros
+/// By default, every field is a Signal.
+/// But this can be a bit concerning if you want to use a `resource` for example.
+/// If you want to use a resource for example, you can use the `modx::resource` procedural macro.
+///
+/// ```
+/// #[modx::resource(age)]
+/// #[modx::store]
+/// struct MyStruct {
+///     name: String, // <- Will be a Signal<String>
+///     age: u8,      // <- Will be a Resource<u8>
+/// }
+/// ```
+///
+/// `modx::props` and `modx::resource` can be written before or after `modx::store`, and their
+/// fields can also be marked with `#[modx(props)]` and `#[modx(resource)]`.
+///
+/// ```
+/// #[modx::store]
+/// struct MyStruct {
+///     name: String,
+///     #[modx(resource)]
+///     age:  u8,
+/// }
+/// ```
+///
+/// ## Dirty tracking
+/// A store remembers the value that every field had when it was created, including the props it
+/// was given, so that it can tell which fields were changed since:
+/// - `x_is_dirty()`, `is_dirty()` and `dirty_fields()`, the names of the changed fields.
+/// - `reset_x()` and `reset()`, to give back their initial value to the fields.
+/// - `mark_clean()`, to use the current values as the initial ones, like after they are saved.
+///
+/// The initial values are kept in signals too, so a component that reads `is_dirty()` is rendered
+/// again when `mark_clean()` changes them.
+///
+/// `reset()` and `mark_clean()` are only as visible as the most private of the setters, since they
+/// write every field. A store with a `readonly` field can only be reset from its module.
+///
+/// Only the signals and the props are tracked: nested stores track their own fields. The values
+/// are compared with `PartialEq`, which is only needed by the methods that tell if a field is
+/// dirty.
+///
+/// ```
+/// #[modx::store]
+/// #[modx::props(name)]
+/// struct ProfileForm {
+///     name: String,
+///     bio:  String,
+/// }
+///
+/// rsx! {
+///     if form.is_dirty() {
+///         "Unsaved changes"
+///         button { onclick: move |_| form.reset(), "Discard" }
+///         button {
+///             onclick: move |_| async move {
+///                 api::save(form.name(), form.bio()).await;
+///                 form.mark_clean();
+///             },
+///             "Save"
+///         }
+///     }
+/// }
+/// ```
+///
+/// ## Outside of hooks
+/// `new` is a hook, so it needs to be called at the top level of a component, and always in the
+/// same order. When a store needs to be created in a loop, an event handler or an async task,
+/// `new_in` and `new_detached` can be used instead. They are not generated for stores with
+/// resources, streams or coroutines, since they can only be created with a hook.
+///
+/// This is also what allows a store to be a child store of a `modx::StoreVec` or a
+/// `modx::StoreMap`.
+///
+/// ```
+/// #[modx::store]
+/// struct ItemStore {
+///     count: i64,
+/// }
+///
+/// // The signals of the store will be dropped with the scope `owner`
+/// let item = ItemStore::new_in(owner);
+/// // The signals of the store will be dropped with the current owner
+/// let item = ItemStore::new_detached();
+/// ```
+///
+/// ## Nested stores
+/// A field with `#[modx(nested)]` is a child store, and is kept as is instead of being wrapped in a
+/// signal, since a store is already a handle to its signals. It's created with the `new` of the
+/// child store, which means that the child store can't take props.
+///
+/// A child store with resources, streams or coroutines can be nested too, but like the child, the
+/// parent can then only be created with `new`: its `new_in` and `new_detached` can't be called.
+///
+/// `modx::StoreVec` and `modx::StoreMap` can also be nested. They need `#[modx(nested)]` too,
+/// otherwise they are wrapped in a signal like any other value, and their child stores aren't
+/// disposed or restored with the parent.
+///
+/// ```
+/// #[modx::store]
+/// struct CountersStore {
+///     #[modx(nested)]
+///     total:    CounterStore,
+///     #[modx(nested)]
+///     counters: StoreVec<CounterStore>,
+/// }
+///
+/// let mut store = CountersStore::new();
+/// store.counters.push(());
+/// store.total.inc();
+/// ```
+///
+/// ## Streams
+/// A field with `#[modx(stream)]` is filled by the `Stream` returned by the async method with the
+/// same name, or by the function given with `#[modx(stream = loader)]`. A `Vec` gets every item
+/// of the stream, and an `Option` only keeps the last one. The stream is read in a resource, so
+/// it's created again, and the field emptied, when a signal read by its method changes.
+///
+/// An item that is an `Err` stops the stream. `field_state()` gives the `modx::StreamState` of the
+/// stream, next to `field_is_done()`, `field_error()` and `restart_field()`. Since the method has
+/// the name of the field, the value is read with `field_value()`, `field_ref()` or
+/// `with_field()`.
+///
+/// ```
+/// #[modx::store]
+/// struct ChatStore {
+///     room: String,
+///     #[modx(stream)]
+///     messages: Vec<Message>,
+/// }
+///
+/// impl ChatStore {
+///     async fn messages(&mut self) -> impl Stream<Item = Result<Message, ChatError>> {
+///         connect(&self.room()).await
+///     }
+/// }
+///
+/// rsx! {
+///     for message in store.messages_value() {
+///         Message { message }
+///     }
+///     if let Some(error) = store.messages_error() {
+///         "Disconnected: {error}"
+///     }
+/// }
+/// ```
+///
+/// ## Coroutines
+/// A field with `#[modx(coroutine)]` is a `Coroutine` that receives messages of the type of the
+/// field. It's run by the async method with the same name, that takes the receiver of the
+/// messages, or by the function given with `#[modx(coroutine = loader)]`. `send_field(message)`
+/// sends it a message, and `restart_field()` runs it again with a new receiver.
+///
+/// The coroutine isn't restarted when the signals it reads change, so it can keep the state of a
+/// long-lived workflow while it reads and writes the store.
+///
+/// ```
+/// enum SyncMessage {
+///     Push(Task),
+///     Retry,
+/// }
+///
+/// #[modx::store]
+/// struct SyncStore {
+///     pending: Vec<Task>,
+///     #[modx(coroutine)]
+///     sync: SyncMessage,
+/// }
+///
+/// impl SyncStore {
+///     async fn sync(&mut self, mut rx: UnboundedReceiver<SyncMessage>) {
+///         while let Some(message) = rx.next().await {
+///             match message {
+///                 SyncMessage::Push(task) => self.pending.write().push(task),
+///                 SyncMessage::Retry => {},
+///             }
+///             let pending = self.pending();
+///             if upload(&pending).await.is_ok() {
+///                 self.pending.write().clear();
+///             }
+///         }
+///     }
+/// }
+///
+/// rsx! {
+///     button { onclick: move |_| store.send_sync(SyncMessage::Retry), "Retry" }
+/// }
+/// ```
+///
+/// ## As component props
+/// A store is only a set of handles to its signals, so two stores are equal when they share the
+/// same signals. This means that a store can directly be used as the props of a component.
+///
+/// ```
+/// #[modx::store]
+/// struct CounterStore {
+///     count: i64,
+/// }
+///
+/// fn Counter(store: CounterStore) -> Element {
+///     rsx! { "{store.count}" }
+/// }
+///
+/// fn app() -> Element {
+///     let store = CounterStore::new();
+///     rsx! { Counter { store } }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn store(attr: OriginalTokenStream, item: OriginalTokenStream) -> OriginalTokenStream {
+    let mut input = parse_macro_input!(item as DeriveInput);
+
+    let mut store_options = StoreOptions::default();
+    let store_options_parser = syn::meta::parser(|meta| store_options.parse(&meta));
+    parse_macro_input!(attr with store_options_parser);
+
+    let marked_fields = match extract_marked_fields(&mut input.attrs, &input.data) {
+        Ok(marked_fields) => marked_fields,
+        Err(why) => return why.to_compile_error().into(),
+    };
+
+    let struct_attrs = &input.attrs;
+    let struct_name = &input.ident;
+    let struct_visibility = &input.vis;
+
+    // Get the fields of the struct
+    let syn::Data::Struct(syn::DataStruct { fields, .. }) = &input.data else {
+        return quote! {
+            compile_error!("Only structs are supported for this macro");
+        }
+        .into();
+    };
+
+    // Data that will be used later on
+    let mut modified_fields = fields.clone();
+    let mut all_idents_types = vec![];
+    let mut props_idents = vec![];
+
+    for field in &mut modified_fields {
+        let mut options = match FieldOptions::extract(field) {
+            Ok(options) => options,
+            Err(why) => return why.to_compile_error().into(),
+        };
+
+        if let Some(ident) = field.ident.clone() {
+            let type_of_field = match options
+                .mark(&marked_fields, &ident)
+                .and_then(|()| options.type_of_field())
+            {
+                Ok(type_of_field) => type_of_field,
+                Err(why) => return why.to_compile_error().into(),
+            };
+
+            all_idents_types.push(StoreField::new(&ident, field, type_of_field, &options));
+
+            // Type of the current field
+            let field_type = field.ty.clone();
+
+            match type_of_field {
+                // Nested stores are already handles to signals, so they are kept as is
+                TypeOfField::Nested => {},
+                TypeOfField::Signal | TypeOfField::Stream => {
+                    field.ty = parse_quote_spanned! { field_type.span()=> Signal<#field_type> };
+                },
+                TypeOfField::Resource => {
+                    field.ty = parse_quote_spanned! { field_type.span()=> Resource<#field_type> };
+                },
+                TypeOfField::Coroutine => {
+                    field.ty = parse_quote_spanned! { field_type.span()=> Coroutine<#field_type> };
+                },
+                TypeOfField::Props => {
+                    field.ty = parse_quote_spanned! { field_type.span()=> Signal<#field_type> };
+                    props_idents.push((ident.clone(), field_type.clone(), field.vis.clone()));
+                },
+            }
+        }
+
+        // The signal itself can't be public, otherwise it could be written from anywhere
+        if options.readonly {
+            field.vis = syn::Visibility::Inherited;
+        }
+    }
+
+    // The hidden fields that pause the refresh of resources, keep their pages, read streams, and
+    // keep the initial values
+    if let syn::Fields::Named(named_fields) = &mut modified_fields {
+        named_fields
+            .named
+            .extend(all_idents_types.iter().filter_map(refresh_field));
+        named_fields
+            .named
+            .extend(all_idents_types.iter().filter_map(pages_field));
+        if !store_options.no_clone {
+            named_fields
+                .named
+                .extend(all_idents_types.iter().filter_map(initial_field));
+        }
+        named_fields.named.extend(
+            all_idents_types
+                .iter()
+                .filter(|StoreField { type_of_field, .. }| *type_of_field == TypeOfField::Stream)
+                .flat_map(stream_fields),
+        );
+    }
+
+    // Implement the accessors
+    let impl_signal_idents = all_idents_types
+        .iter()
+        .map(|store_field| impl_field_methods(struct_name, store_field, store_options.no_clone));
+
+    // Implement default values if there is "default"
+    let impl_default = {
+        // The resources, streams and coroutines that we need to assign just after creation, since
+        // they need the store
+        let alter_resources = all_idents_types.iter().filter_map(|store_field| {
+            match store_field.type_of_field {
+                TypeOfField::Resource => {
+                    Some(new_resource(store_field, store_options.timer.as_ref()))
+                },
+                TypeOfField::Stream => Some(new_stream(store_field)),
+                TypeOfField::Coroutine => Some(new_coroutine(store_field)),
+                _ => None,
+            }
+        });
+
+        let (structprops, props_ty) =
+            impl_props_struct(struct_name, struct_visibility, &props_idents);
+        let props_param = props_ty.as_ref().map(|ty| quote! { props: #ty, });
+
+        // A store can only be nested in another one if it can be created without props
+        let impl_nested = if props_ty.is_none() {
+            quote! {
+                impl ::modx::Nested for #struct_name {
+                    fn use_nested() -> Self {
+                        Self::new()
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        let hook_signals = default_signals(&all_idents_types, Constructor::Hook);
+        let hook_values =
+            default_values(&all_idents_types, Constructor::Hook, store_options.no_clone);
+
+        let impl_new_outside_hooks = impl_new_outside_hooks(
+            struct_name,
+            props_ty.as_ref(),
+            &all_idents_types,
+            store_options.no_clone,
+        );
+
+        quote! {
+            #structprops
+
+            impl #struct_name {
+                pub fn new(#props_param) -> Self {
+                    #(#hook_signals)*
+                    let mut default_struct = #struct_name {
+                        #(#hook_values)*
+                    };
+
+                    #(#alter_resources)*
+
+                    default_struct
+                }
+            }
+
+            #impl_nested
+
+            #impl_new_outside_hooks
+        }
+    };
+
+    let impl_props = impl_props(
+        struct_name,
+        struct_visibility,
+        all_idents_types
+            .iter()
+            .map(|StoreField { ident, .. }| ident),
+        &format_ident!("store"),
+    );
+    let impl_refresh_all = impl_refresh_all(struct_name, &all_idents_types);
+    let impl_snapshot = impl_snapshot(struct_name, &all_idents_types, store_options.no_clone);
+    // The initial values are cloned, so they aren't kept with `no_clone`
+    let impl_dirty = if store_options.no_clone {
+        quote! {}
+    } else {
+        impl_dirty(struct_name, struct_visibility, &all_idents_types)
+    };
+    let marked_paths = &marked_fields.paths;
+    let impl_view = impl_view(
+        struct_name,
+        struct_visibility,
+        &all_idents_types,
+        store_options.no_clone,
+    );
+
+    quote! {
+        #(#struct_attrs)*
+        #[derive(Copy, Clone)]
+        #struct_visibility struct #struct_name
+            #modified_fields
+
+        #(#impl_signal_idents)*
+
+        #impl_default
+
+        #impl_props
+
+        #impl_view
+
+        #impl_refresh_all
+
+        #impl_snapshot
+
+        #impl_dirty
+
+        #(#[allow(unused_imports)] use #marked_paths as _;)*
+    }
+    .into()
+}
+
+/// The fields marked by the `props` and `resource` attributes that come after `store`.
+#[derive(Default)]
+struct MarkedFields {
+    props: Vec<Arg>,
+    resource: Vec<Arg>,
+    /// Paths of the attributes, like `modx::props`.
+    paths: Vec<syn::Path>,
+}
+
+/// Remove the `props` and `resource` attributes of a store, and return the fields they mark.
+///
+/// `props` and `resource` mark the fields themselves when they come before `store`. When they come
+/// after it, they haven't been expanded yet, so they are read here.
+fn extract_marked_fields(
+    attrs: &mut Vec<syn::Attribute>,
+    data: &syn::Data,
+) -> syn::Result<MarkedFields> {
+    let mut marked_fields = MarkedFields::default();
+    let mut other_attrs = vec![];
+
+    for attr in std::mem::take(attrs) {
+        let (marker, marked) = if is_modx_macro(&attr, "props") {
+            ("props", &mut marked_fields.props)
+        } else if is_modx_macro(&attr, "resource") {
+            ("resource", &mut marked_fields.resource)
+        } else {
+            other_attrs.push(attr);
+            continue;
+        };
+
+        let args = attr.parse_args::<Args>()?;
+        if let syn::Data::Struct(syn::DataStruct { fields, .. }) = data {
+            args.check(fields, marker)?;
+        }
+        marked.extend(args.vars);
+
+        // The macro is never expanded, so its import would be seen as unused
+        marked_fields.paths.push(attr.path().clone());
+    }
+
+    *attrs = other_attrs;
+    Ok(marked_fields)
+}
+
+/// Implement the struct `{Name}Props`, that takes the props of the store.
+///
+/// If there is no field that should be used as a props, the constructors take no parameter, so
+/// nothing is implemented and no type is returned.
+fn impl_props_struct(
+    struct_name: &Ident,
+    struct_visibility: &syn::Visibility,
+    props_idents: &[(Ident, syn::Type, syn::Visibility)],
+) -> (TokenStream, Option<syn::Type>) {
+    if props_idents.is_empty() {
+        return (quote! {}, None);
+    }
+
+    let structprops_name = format_ident!("{struct_name}Props");
+
+    let (structprops_params, structprops_field_inits): (Vec<_>, Vec<_>) = props_idents
+        .iter()
+        .map(|(ident, ty, _)| (quote! ( #ident: #ty, ), quote! ( #ident, )))
+        .unzip();
+    // Props have the same visibility as their field in the store
+    let structprops_fields = props_idents
+        .iter()
+        .map(|(ident, ty, vis)| quote! ( #vis #ident: #ty, ));
+
+    let structprops = quote! {
+        #[derive(Debug)]
+        #struct_visibility struct #structprops_name {
+            #(#structprops_fields)*
+        }
+        impl #structprops_name {
+            pub fn new(#(#structprops_params)*) -> Self {
+                Self {
+                    #(#structprops_field_inits)*
+                }
+            }
+        }
+    };
+
+    (structprops, Some(parse_quote! { #structprops_name }))
+}
+
+/// Implement the methods of a field, that depend on what the field is.
+fn impl_field_methods(
+    struct_name: &Ident,
+    store_field: &StoreField,
+    no_clone: bool,
+) -> TokenStream {
+    let StoreField {
+        ident,
+        ty,
+        read_vis,
+        write_vis,
+        type_of_field,
+        ..
+    } = store_field;
+    match type_of_field {
+        TypeOfField::Signal | TypeOfField::Props => {
+            let accessors = impl_accessors(struct_name, ident, ty, read_vis, no_clone);
+            let setters = impl_setters(struct_name, ident, ty, write_vis);
+            let mutators = impl_mutators(struct_name, ident, ty, write_vis);
+            quote! {
+                #accessors
+                #setters
+                #mutators
+            }
+        },
+        TypeOfField::Nested => {
+            quote! {
+                impl #struct_name {
+                    #read_vis fn #ident(&self) -> #ty {
+                        self.#ident
+                    }
+                }
+            }
+        },
+        TypeOfField::Resource => impl_resource_helpers(struct_name, store_field, no_clone),
+        TypeOfField::Stream => impl_stream_helpers(struct_name, store_field, no_clone),
+        TypeOfField::Coroutine => impl_coroutine_helpers(struct_name, store_field),
+    }
+}
+
+/// Implement the accessors of a field that is a signal.
+///
+/// `field()` clones the value, while `field_ref()` and `with_field()` only borrow it, which also
+/// works for types that aren't `Clone`.
+fn impl_accessors(
+    struct_name: &Ident,
+    ident: &Ident,
+    ty: &syn::Type,
+    vis: &syn::Visibility,
+    no_clone: bool,
+) -> TokenStream {
+    let ref_ident = format_ident!("{ident}_ref");
+    let with_ident = format_ident!("with_{ident}");
+
+    let clone_getter = if no_clone {
+        quote! {}
+    } else {
+        quote! {
+            #vis fn #ident(&self) -> #ty {
+                self.#ident.read().clone()
+            }
+        }
+    };
+
+    quote! {
+        impl #struct_name {
+            #clone_getter
+
+            #vis fn #ref_ident(&self) -> ReadableRef<'_, Signal<#ty>> {
+                self.#ident.read()
+            }
+
+            #vis fn #with_ident<O, F: FnOnce(&#ty) -> O>(&self, f: F) -> O {
+                f(&self.#ident.read())
+            }
+        }
+    }
+}
+
+/// The signals of the fields that are neither props nor loaded, created with `constructor` before
+/// the literal of the store.
+///
+/// Their default value is only created once: the signal keeps it, and the initial value of the
+/// field is a clone of it, like for a prop.
+fn default_signals(all_idents_types: &[StoreField], constructor: Constructor) -> Vec<TokenStream> {
+    all_idents_types
+        .iter()
+        .filter(|StoreField { type_of_field, .. }| *type_of_field == TypeOfField::Signal)
+        .map(|StoreField { ident, ty, .. }| {
+            let signal = constructor.new_signal(&default_value(ty));
+            let default = default_signal_ident(ident);
+            quote! { let #default = #signal; }
+        })
+        .collect()
+}
+
+/// Initial value of every field of a store, when it's created with `constructor`. The signals of
+/// the fields that are neither props nor loaded come from [`default_signals`].
+///
+/// Unless the store is `no_clone`, the initial values of the signals and props are also kept
+/// aside, to tell if the fields are dirty.
+fn default_values(
+    all_idents_types: &[StoreField],
+    constructor: Constructor,
+    no_clone: bool,
+) -> Vec<TokenStream> {
+    all_idents_types
+        .iter()
+        .map(|store_field| {
+            let StoreField {
+                ident,
+                ty,
+                type_of_field,
+                ..
+            } = store_field;
+
+            match type_of_field {
+                TypeOfField::Signal => {
+                    let default = default_signal_ident(ident);
+                    let initial = (!no_clone).then(|| {
+                        let value = quote!(::core::clone::Clone::clone(&*#default.peek()));
+                        initial_value(store_field, constructor, &value)
+                    });
+                    quote! { #initial #ident: #default, }
+                },
+                TypeOfField::Props => {
+                    // The prop is cloned before it's moved into the signal
+                    let initial = (!no_clone).then(|| {
+                        let value = quote!(::core::clone::Clone::clone(&props.#ident));
+                        initial_value(store_field, constructor, &value)
+                    });
+                    let signal = constructor.new_signal(&quote!(props.#ident));
+                    quote! { #initial #ident: #signal, }
+                },
+                TypeOfField::Nested => {
+                    let nested = constructor.new_nested(ty);
+                    quote! { #ident: #nested, }
+                },
+                // TODO: Change case
+                TypeOfField::Resource => default_resource(store_field),
+                TypeOfField::Stream => default_stream(store_field, &default_value(ty)),
+                TypeOfField::Coroutine => default_coroutine(store_field),
+            }
+        })
+        .collect()
+}
+
+/// The local that holds the signal of a field until the store is created, named so that it
+/// can't shadow the arguments of the constructor.
+fn default_signal_ident(ident: &Ident) -> Ident {
+    format_ident!("_modx_default_{ident}")
+}
+
+/// `ty::default()`, with the generics of `ty` written as in an expression.
+fn default_value(ty: &syn::Type) -> TokenStream {
+    let ty_corrected = quote!(#ty).to_string().replace('<', "::<");
+    match syn::parse_str::<syn::Type>(&ty_corrected) {
+        Ok(parsed_type) => quote!(#parsed_type::default()),
+        Err(why) => why.to_compile_error(),
+    }
+}
+
+/// Implement `new_in` and `new_detached`, that create a store without calling any hook, and the
+/// `modx::Store` and `modx::NestedOutsideHooks` traits.
+///
+/// A resource can only be created with the `use_resource` hook, so stores with resources,
+/// streams or coroutines can't be created outside of the body of a component.
+fn impl_new_outside_hooks(
+    struct_name: &Ident,
+    props_ty: Option<&syn::Type>,
+    all_idents_types: &[StoreField],
+    no_clone: bool,
+) -> TokenStream {
+    if all_idents_types
+        .iter()
+        .any(|StoreField { type_of_field, .. }| {
+            matches!(
+                type_of_field,
+                TypeOfField::Resource | TypeOfField::Stream | TypeOfField::Coroutine
+            )
+        })
+    {
+        return quote! {};
+    }
+
+    let in_scope_signals = default_signals(all_idents_types, Constructor::InScope);
+    let in_scope_values = default_values(all_idents_types, Constructor::InScope, no_clone);
+    let detached_signals = default_signals(all_idents_types, Constructor::Detached);
+    let detached_values = default_values(all_idents_types, Constructor::Detached, no_clone);
+    let dispose_fields = all_idents_types.iter().map(
+        |StoreField {
+             ident,
+             type_of_field,
+             ..
+         }| {
+            if *type_of_field == TypeOfField::Nested {
+                quote! { ::modx::NestedOutsideHooks::dispose_nested(self.#ident); }
+            } else if !no_clone && is_tracked(*type_of_field) {
+                let initial = initial_ident(ident);
+                quote! {
+                    self.#ident.manually_drop();
+                    self.#initial.manually_drop();
+                }
+            } else {
+                quote! { self.#ident.manually_drop(); }
+            }
+        },
+    );
+
+    let props_param = props_ty.map(|ty| quote! { props: #ty, });
+
+    // The nested stores are only checked when the store is created outside of hooks, so that a
+    // child store with resources can still be nested
+    let nested_bounds = all_idents_types
+        .iter()
+        .filter(|StoreField { type_of_field, .. }| *type_of_field == TypeOfField::Nested)
+        .map(|StoreField { ty, .. }| quote! { for<'a> #ty: ::modx::NestedOutsideHooks })
+        .collect::<Vec<_>>();
+
+    // A store can only be nested in another one if it can be created without props
+    let impl_nested = if props_ty.is_none() {
+        quote! {
+            impl ::modx::NestedOutsideHooks for #struct_name where #(#nested_bounds,)* {
+                fn new_nested(owner: Option<ScopeId>) -> Self {
+                    match owner {
+                        Some(owner) => Self::new_in(owner),
+                        None => Self::new_detached(),
+                    }
+                }
+
+                fn dispose_nested(self) {
+                    ::modx::Store::dispose(self);
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let (store_props_ty, store_props_pat, store_props_arg) = match props_ty {
+        Some(ty) => (quote! { #ty }, quote! { props }, quote! { props }),
+        None => (quote! { () }, quote! { () }, quote! {}),
+    };
+
+    quote! {
+        impl #struct_name {
+            /// Create the store without calling any hook.
+            /// Its signals are owned by the scope `owner`, and are dropped with it.
+            pub fn new_in(owner: ScopeId, #props_param) -> Self where #(#nested_bounds,)* {
+                #(#in_scope_signals)*
+                #struct_name {
+                    #(#in_scope_values)*
+                }
+            }
+
+            /// Create the store without calling any hook.
+            /// Its signals are owned by the current owner, which is usually the current
+            /// component.
+            pub fn new_detached(#props_param) -> Self where #(#nested_bounds,)* {
+                #(#detached_signals)*
+                #struct_name {
+                    #(#detached_values)*
+                }
+            }
+        }
+
+        impl ::modx::Store for #struct_name where #(#nested_bounds,)* {
+            type Props = #store_props_ty;
+
+            fn new_in(owner: ScopeId, #store_props_pat: Self::Props) -> Self {
+                Self::new_in(owner, #store_props_arg)
+            }
+
+            fn dispose(self) {
+                #(#dispose_fields)*
+            }
+        }
+
+        #impl_nested
+    }
+}
+
+/// Implement `PartialEq` and `Properties` for a store, or for its view.
+///
+/// Stores are handles to signals, so two stores are equal when they point to the same signals.
+/// This is what Dioxus needs to memoize a component that takes the store as its props.
+/// In `rsx`, the store is given to the component with the field `prop_name`.
+fn impl_props<'a>(
+    struct_name: &Ident,
+    struct_visibility: &syn::Visibility,
+    idents: impl Iterator<Item = &'a Ident>,
+    prop_name: &Ident,
+) -> TokenStream {
+    let builder_name = format_ident!("{struct_name}Builder");
+
+    quote! {
+        impl PartialEq for #struct_name {
+            fn eq(&self, other: &Self) -> bool {
+                true #(&& self.#idents == other.#idents)*
+            }
+        }
+
+        impl Properties for #struct_name {
+            type Builder = #builder_name;
+
+            fn builder() -> Self::Builder {
+                #builder_name(())
+            }
+
+            fn memoize(&mut self, other: &Self) -> bool {
+                let equal = self == other;
+                if !equal {
+                    *self = *other;
+                }
+                equal
+            }
+        }
+
+        #[doc(hidden)]
+        #struct_visibility struct #builder_name<S = ()>(S);
+
+        impl #builder_name {
+            pub fn #prop_name(self, #prop_name: #struct_name) -> #builder_name<#struct_name> {
+                #builder_name(#prop_name)
+            }
+        }
+
+        impl #builder_name<#struct_name> {
+            pub fn build(self) -> #struct_name {
+                self.0
+            }
+        }
+    }
+}
+
+/// Get resources with a function
+///
+/// ## Usage
+/// ```
+/// #[modx::resource(fetch_cat_url)]
+/// #[modx::store]
+/// struct MyStore {
+///     number_of_cats: usize,
+///     fetch_cat_url: Result<String>,
+/// }
+///
+/// impl MyStore {
+///     async fn fetch_cat_url(&mut self) -> Result<String> {
+///         self.number_of_cats += 1;
+///
+///         reqwest::get("http://localhost/cat")
+///             .await
+///             .unwrap()
+///             .json::<ApiResponse>()
+///             .await
+///     }
+/// }
+///
+/// let store = MyStore::new();
+/// match &*store.fetch_cat_url.read()  {
+///     Some(Ok(url)) =>
+///         rsx! {
+///             div {
+///                 img {
+///                     max_width: "500px",
+///                     max_height: "500px",
+///                     src: url
+///                 }
+///             }
+///             "Cat N°{store.number_of_cats}"
+///         },
+///     Some(Err(_)) => rsx! { "An error occured while getting a cat :(" },
+///     None => rsx!( "No cat for now." ),
+/// }
+/// ```
+///
+/// ## Attributes
+/// - Every attributes passed in the `resource` procedural macro needs to be implemented as a function
+///   in this particular struct and also being a field of this struct with the proper type.
+///
+/// - Functions that are concerned by this macro need to be async and shouldn't take any parameter,
+///   other than a token with `token`. Their signature is checked, and a wrong one is reported on
+///   the field.
+///
+/// - It needs to be used along with `#[modx::store]`, before or after it. A field can also be
+///   marked with `#[modx(resource)]` instead.
+///
+/// ## Loaders
+/// A resource can also be loaded by any async function that takes the store, with
+/// `#[modx::resource(field = loader)]` or `#[modx(resource = loader)]`. The loader can be a
+/// function, a method written as `Self::method`, or an async closure, so that one function can
+/// load the resources of several stores.
+///
+/// ```
+/// async fn load_avatar(store: &mut UserStore) -> String {
+///     api::avatar(store.id()).await
+/// }
+///
+/// #[modx::resource(avatar = load_avatar)]
+/// #[modx::store]
+/// struct UserStore {
+///     id:     u64,
+///     avatar: String,
+///     #[modx(resource = async |store: &mut UserStore| api::name(store.id()).await)]
+///     name:   String,
+/// }
+/// ```
+///
+/// ## Retries and timeouts
+/// A resource that is a `Result` can be retried when it fails, and time out:
+/// - `retry = 3` retries a failed load up to 3 times.
+/// - `backoff = "fixed"`, `"linear"` or `"exponential"` (the default) is how the delay between two
+///   attempts grows, starting at `retry_delay_ms = 100`.
+/// - `timeout_ms = 5000` makes a load fail with `modx::TimedOut` when it takes too long, so its
+///   error needs to implement `From<modx::TimedOut>`. Each attempt has its own timeout.
+///
+/// Waiting is done with a `modx::Timer`, which is `modx::ThreadTimer` by default. Another timer
+/// can be given with `timer = MyTimer`, or with `#[modx::store(timer = MyTimer)]` for every
+/// resource of the store, and must be on the web where `modx::ThreadTimer` doesn't exist. A timer
+/// that resolves at once allows to test these resources without waiting.
+///
+/// ```
+/// #[modx::store]
+/// struct CatStore {
+///     #[modx(resource, retry = 3, backoff = "exponential", timeout_ms = 5000)]
+///     fetch_cat: Result<ApiResponse, ApiError>,
+/// }
+/// ```
+///
+/// ## Refresh
+/// `refresh_every = "30s"` loads the resource again at this interval, with its timer, for as long as
+/// the component that created the store is alive. The interval is a number above zero followed by
+/// `ms`, `s`, `m` or `h`. A load that isn't finished yet is not restarted.
+///
+/// The refresh pauses by itself while the resource isn't read anymore. Once the resource has been
+/// read through its helpers, like `x_value()`, `x_suspend()` or `x_state()`, it's only loaded again
+/// if it was read since its previous load, which it is while a component displays it, since each
+/// load rerenders the components that read it. A component that displays it again resumes the
+/// refresh. Reading the field directly, like `store.x.read()`, isn't seen by the store.
+///
+/// The refresh can also be paused with `pause_refresh_x()` and `resume_refresh_x()`, or
+/// `pause_refresh()` and `resume_refresh()` for every resource of the store. Those are only as
+/// visible as the most private of the resources, like `reset()`.
+///
+/// ```
+/// #[modx::store]
+/// struct DashboardStore {
+///     #[modx(resource = api::stats, refresh_every = "30s")]
+///     stats: Result<Stats, ApiError>,
+/// }
+///
+/// // Only refreshed while this component is displayed
+/// #[component]
+/// fn Stats(store: DashboardStore) -> Element {
+///     match store.stats_value() {
+///         Some(Ok(stats)) => rsx! { "{stats}" },
+///         Some(Err(_)) => rsx! { "The stats can't be loaded" },
+///         None => rsx! { "Loading..." },
+///     }
+/// }
+///
+/// // Not refreshed while the page is in the background
+/// store.pause_refresh();
+/// ```
+///
+/// ## Debounce
+/// `debounce_ms = 300` waits before loading the resource, so that the signals it reads can change
+/// several times, like the query of a search bar, and only load it once with their last value.
+///
+/// ```
+/// #[modx::store]
+/// struct SearchStore {
+///     query:   String,
+///     #[modx(resource = api::search, debounce_ms = 300)]
+///     results: Result<Vec<String>, ApiError>,
+/// }
+/// ```
+///
+/// To debounce or throttle a method, see [`macro@action`].
+///
+/// ## Concurrency
+/// By default, a resource that restarts cancels the load that is running, so that the value of
+/// an older load never replaces a newer one. `concurrency` changes this:
+/// - `"queue"` waits for the load that is running before loading again.
+/// - `"drop_new"` ignores the restarts while a load is running, and keeps its value.
+/// - `"parallel"` lets the previous loads finish, but only keeps the value of the last one.
+///
+/// ```
+/// #[modx::store]
+/// struct OrderStore {
+///     items:   Vec<Item>,
+///     #[modx(resource = api::checkout, concurrency = "drop_new")]
+///     receipt: Result<Receipt, ApiError>,
+/// }
+/// ```
+///
+/// With `token`, the method or the loader also takes a `modx::CancellationToken` as its last
+/// argument, after the cursor of a paginated field. The store can't see the signature of the
+/// method, so unlike an action, this needs to be written on the field. The token is cancelled when
+/// the load is cancelled: when the resource restarts without `concurrency`, or by a newer load with
+/// `"latest"`. A cancelled load is stopped at its next `.await`, so the token is mostly useful to
+/// the work it gave to something else, like a thread.
+///
+/// ```
+/// #[modx::store]
+/// struct ReportStore {
+///     #[modx(resource, token)]
+///     report: Result<Report, ApiError>,
+/// }
+///
+/// impl ReportStore {
+///     async fn report(&self, token: modx::CancellationToken) -> Result<Report, ApiError> {
+///         api::build_report(token).await
+///     }
+/// }
+/// ```
+///
+/// ## Cache
+/// `key = |store| ...` shares the value of the resource between every resource with the same key
+/// and type, in any store:
+/// - Two resources that load the same key at the same time only load it once.
+/// - A value loaded less than `ttl_ms` ago is used without loading it again. It's stale right away
+///   by default.
+/// - A stale value is shown while a new one is loaded, and every resource with this key gets the
+///   new one.
+/// - The values of a key are dropped once no resource uses it, when the components of their
+///   stores are unmounted.
+///
+/// The key is read inside the resource, so the resource is loaded again when it changes.
+/// `modx::invalidate(&key)` marks the values of a key as stale and loads them again, and the
+/// resource also gets `invalidate_x()` for its current key. The value needs to be `Clone`.
+///
+/// ```
+/// #[modx::store]
+/// struct UserStore {
+///     user_id: u64,
+///     #[modx(resource = api::user, key = |store| ("user", store.user_id()), ttl_ms = 60_000)]
+///     user:    Result<User, ApiError>,
+/// }
+///
+/// // After the user is edited
+/// modx::invalidate(&("user", user_id));
+/// ```
+///
+/// ## Pagination
+/// A field with `#[modx(paginated)]` is a resource that loads a list one page at a time. Its type
+/// is a `Result<modx::Page<T, C>, E>`, and its method takes the cursor `Option<C>` of the page,
+/// which is `None` for the first one. A page gives its items and the cursor of the next page, if
+/// there is one.
+///
+/// The items of every loaded page are read with `x_items()`, and `load_more_x()` loads the next
+/// page, unless a page is loading or there is no next page. `x_has_more()` and
+/// `x_is_loading_more()` tell when to show a "Load more" button or a spinner. When a signal read
+/// by the method changes, the first page is loaded again, even if `load_more_x()` was called along
+/// with it, and replaces the items once it's loaded, while `reset_x()` forgets the items right
+/// away. The other helpers are about the last page
+/// loaded. The items and the cursor need to be `Clone`, and a paginated field can't have a `key`.
+///
+/// ```
+/// #[modx::store]
+/// struct TodoListStore {
+///     search: String,
+///     #[modx(paginated)]
+///     todos:  Result<modx::Page<Todo, u32>, ApiError>,
+/// }
+///
+/// impl TodoListStore {
+///     async fn todos(&mut self, cursor: Option<u32>) -> Result<modx::Page<Todo, u32>, ApiError> {
+///         let page = api::todos(&self.search(), cursor.unwrap_or(0)).await?;
+///         Ok(modx::Page { items: page.todos, next: page.next_offset })
+///     }
+/// }
+///
+/// rsx! {
+///     for todo in store.todos_items() {
+///         Todo { todo }
+///     }
+///     if store.todos_is_loading_more() {
+///         "Loading..."
+///     } else if store.todos_has_more() {
+///         button { onclick: move |_| store.load_more_todos(), "Load more" }
+///     }
+/// }
+/// ```
+///
+/// ## Helpers
+/// Every resource `x` gets some helpers, so that its value doesn't need to be matched everywhere:
+/// - `x_value()`, the value of the resource, or `None` if it isn't loaded yet.
+/// - `x_ok()` and `x_err()`, when the resource is a `Result`.
+/// - `x_is_loading()` and `x_state()`.
+/// - `reload_x()`, to load the resource again, and `clear_x()`, to forget its value.
+///
+/// `x_value`, `x_ok` and `x_err` clone the value, so they can only be used when it is `Clone`.
+///
+/// ```
+/// rsx! {
+///     if let Some(url) = store.fetch_cat_url_ok() {
+///         img { src: url }
+///     } else if store.fetch_cat_url_is_loading() {
+///         "Loading..."
+///     }
+///     button { onclick: move |_| store.reload_fetch_cat_url(), "Another cat!" }
+/// }
+/// ```
+///
+/// ```
+/// #[component]
+/// fn Cat(store: CatStore) -> Element {
+///     let url = store.fetch_cat_url_throw()?;
+///     rsx! { img { src: url } }
+/// }
+///
+/// rsx! {
+///     ErrorBoundary {
+///         handle_error: |_| rsx! { "An error occured while getting a cat :(" },
+///         SuspenseBoundary {
+///             fallback: |_| rsx! { "Loading..." },
+///             Cat { store }
+///         }
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn resource(attr: OriginalTokenStream, item: OriginalTokenStream) -> OriginalTokenStream {
+    mark_fields(attr, item, "resource")
+}
+
+/// Add some props to a modx store
+///
+/// ## Usage
+/// ```
+/// // Adds price and name a props o the component
+/// #[modx::props(price, name)]
+/// #[modx::store]
+/// struct MyStore {
+///     price: usize,
+///     name: String,
+///     sold_today: usize,
+/// }
+///
+/// // Create the store with props
+/// let store = MyStore::new(
+///     MyStoreProps{
+///         price: 10,
+///         name: String::from("item")
+///     }
+/// )
+/// ```
+///
+/// ## Attributes
+/// This procedural macro automatically creates a struct with the same name as the original struct +
+/// `Props` in suffix, that will have in field, all the props defined in the `#[modx::props]` macro.
+///
+/// Every props is still a signal so you can easily modify them, copy them and see the changes.
+///
+/// It needs to be used along with `#[modx::store]`, before or after it. A field can also be marked
+/// with `#[modx(props)]` instead.
+#[proc_macro_attribute]
+pub fn props(attr: OriginalTokenStream, item: OriginalTokenStream) -> OriginalTokenStream {
+    mark_fields(attr, item, "props")
+}
+
+/// Add `#[modx(#marker)]` to the fields given in `attr`, so that `store` knows what they are once
+/// it is expanded.
+fn mark_fields(
+    attr: OriginalTokenStream,
+    item: OriginalTokenStream,
+    marker: &str,
+) -> OriginalTokenStream {
+    let args = parse_macro_input!(attr as Args);
+    let mut input = parse_macro_input!(item as DeriveInput);
+
+    // `store` reads the `props` and `resource` that come after it, so this is only expanded when it
+    // comes before `store`
+    if !input.attrs.iter().any(|attr| is_modx_macro(attr, "store")) {
+        let message = format!("`#[modx::{marker}]` can only be used along with `#[modx::store]`");
+        return quote! {
+            compile_error!(#message);
+        }
+        .into();
+    }
+
+    // Get the fields of the struct
+    let syn::Data::Struct(syn::DataStruct { fields, .. }) = &mut input.data else {
+        return quote! {
+            compile_error!("Only structs are supported for this macro");
+        }
+        .into();
+    };
+
+    if let Err(why) = args.check(fields, marker) {
+        return why.to_compile_error().into();
+    }
+
+    for field in fields.iter_mut() {
+        // The marker has the span of the name given to the macro, so that errors point at it
+        if let Some(Arg { ident, loader }) = field.ident.as_ref().and_then(|ident| args.get(ident))
+        {
+            let marker = Ident::new(marker, ident.span());
+            let loader = loader.as_ref().map(|loader| quote! { = #loader });
+            field.attrs.push(parse_quote! { #[modx(#marker #loader)] });
+        }
+    }
+
+    quote! { #input }.into()
+}
+
+/// If `attr` is the modx macro `name`, written as `#[name]` or `#[modx::name]`.
+fn is_modx_macro(attr: &syn::Attribute, name: &str) -> bool {
+    let path = attr.path();
+    match path.segments.len() {
+        1 => path.is_ident(name),
+        2 => path.segments[0].ident == "modx" && path.segments[1].ident == name,
+        _ => false,
+    }
+}
+
+/// Debounce or throttle a method of a store.
+///
+/// ## Usage
+/// ```
+/// #[modx::store]
+/// struct SearchStore {
+///     query:   String,
+///     results: Vec<String>,
+/// }
+///
+/// impl SearchStore {
+///     // Only runs once no other search was made for 300ms
+///     #[modx::action(debounce_ms = 300)]
+///     async fn search(&mut self, query: String) {
+///         self.results.set(api::search(&query).await);
+///     }
+///
+///     // Runs at most once every second, and the calls in between are dropped
+///     #[modx::action(throttle_ms = 1000)]
+///     fn save(&mut self) {
+///         storage::save(&self.query());
+///     }
+/// }
+/// ```
+///
+/// ## Attributes
+/// - `debounce_ms = 300` runs the method once it wasn't called for this delay, with the arguments
+///   of the last call.
+/// - `throttle_ms = 1000` runs the method at once, unless a previous call started less than this
+///   delay ago, or is still running.
+/// - `concurrency = "latest"`, `"queue"`, `"drop_new"` or `"parallel"` (the default) is what
+///   happens when the method is called while a previous call is still running. `latest` cancels
+///   the previous calls, `queue` runs the calls one after the other, and `drop_new` drops the new
+///   call. It can be used alone, or along with `debounce_ms` or `throttle_ms`.
+/// - `optimistic` restores the store if the method returns an `Err`, see below. It can't be used
+///   along with the other options, other than `timer`.
+/// - `timer = MyTimer` is the `modx::Timer` that waits, `modx::ThreadTimer` by default.
+///
+/// The calls are counted separately for each store. Since the method may run later, in a task of
+/// the current component, it can't return a value, and its arguments need to be `'static`. It can
+/// also be async.
+///
+/// ## Optimistic updates
+/// An `optimistic` action takes a snapshot of the store before it runs, and restores it if the
+/// method returns an `Err`. There is no separate macro for async methods: `optimistic` is an option
+/// of `#[modx::action]`, like the others.
+///
+/// The method runs at once, in the call, until its first `.await`. So the changes it makes before
+/// waiting for the backend are shown right away, even while a previous call is still waiting. The
+/// rest of the calls is queued, so that they finish one after the other.
+///
+/// The snapshot has the values of every field, along with the ones of the nested stores, but not
+/// of the resources. A child store removed from a `StoreVec` or a `StoreMap` is created again with
+/// new signals. The values of the fields need to be `Clone`.
+///
+/// A failed call restores the store as it was before it, so the changes of the calls made after it
+/// are undone too, since they were made on top of it. Those that are still queued are cancelled:
+/// they don't run past their first `.await`, and their `modx::CancellationToken` is cancelled. The
+/// changes of the calls made before it are kept.
+///
+/// ```
+/// impl TodoStore {
+///     #[modx::action(optimistic)]
+///     async fn add(&mut self, name: String) -> Result<(), ApiError> {
+///         self.tasks.push(TaskStoreProps { name: name.clone() });
+///         api::add_task(&name).await
+///     }
+/// }
+///
+/// // The task is shown at once, and removed if the backend rejects it
+/// store.add(String::from("Buy a cat"));
+/// ```
+///
+/// The value returned by the method is only used to know if it failed, so the action itself doesn't
+/// return it.
+///
+/// ## Cancellation
+/// If the last argument of the method is a `modx::CancellationToken`, it is given by the action
+/// instead of the caller, and is cancelled when a newer call cancels this one. A cancelled call is
+/// stopped at its next `.await`, so the token is mostly useful to the work it gave to a thread or
+/// to another task.
+///
+/// ```
+/// impl SearchStore {
+///     #[modx::action(concurrency = "latest")]
+///     async fn index(&mut self, token: modx::CancellationToken) {
+///         let files = self.files();
+///         std::thread::spawn(move || {
+///             for file in files {
+///                 if token.is_cancelled() {
+///                     return;
+///                 }
+///                 index_file(file);
+///             }
+///         });
+///     }
+/// }
+///
+/// store.index();
+/// ```
+#[proc_macro_attribute]
+pub fn action(attr: OriginalTokenStream, item: OriginalTokenStream) -> OriginalTokenStream {
+    let mut action_options = ActionOptions::default();
+    let action_options_parser = syn::meta::parser(|meta| action_options.parse(&meta));
+    parse_macro_input!(attr with action_options_parser);
+
+    let method = parse_macro_input!(item as syn::ImplItemFn);
+
+    impl_action(action_options, method)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,193 @@
+use {
+    proc_macro2::TokenStream,
+    quote::{format_ident, quote},
+    syn::{GenericArgument, Ident, PathArguments, Type, Visibility},
+};
+
+/// The types of fields that have specific mutators.
+enum MutableType<'a> {
+    Bool,
+    Integer,
+    Float,
+    Vec(&'a Type),
+    Map(&'a Type, &'a Type),
+    Option(&'a Type),
+}
+
+impl<'a> MutableType<'a> {
+    /// Guess the type of a field from its path. Aliases of these types are not detected.
+    fn from_type(ty: &'a Type) -> Option<Self> {
+        let Type::Path(type_path) = ty else {
+            return None;
+        };
+        if type_path.qself.is_some() {
+            return None;
+        }
+
+        let segment = type_path.path.segments.last()?;
+        let generics = match &segment.arguments {
+            PathArguments::AngleBracketed(args) => {
+                args.args
+                    .iter()
+                    .filter_map(|arg| {
+                        match arg {
+                            GenericArgument::Type(ty) => Some(ty),
+                            _ => None,
+                        }
+                    })
+                    .collect()
+            },
+            _ => vec![],
+        };
+
+        match (segment.ident.to_string().as_str(), generics.as_slice()) {
+            ("bool", []) => Some(Self::Bool),
+            (
+                "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" |
+                "u128" | "usize",
+                [],
+            ) => Some(Self::Integer),
+            ("f32" | "f64", []) => Some(Self::Float),
+            ("Vec", [item]) => Some(Self::Vec(item)),
+            ("HashMap" | "BTreeMap", [key, value, ..]) => Some(Self::Map(key, value)),
+            ("Option", [inner]) => Some(Self::Option(inner)),
+            _ => None,
+        }
+    }
+}
+
+/// Implement the setters of a field, with the visibility `vis`.
+///
+/// `Option` fields already have a `replace_x` that takes the inner value, so they don't get the
+/// one that takes the whole value.
+pub fn impl_setters(
+    struct_name: &Ident,
+    ident: &Ident,
+    ty: &Type,
+    vis: &Visibility,
+) -> TokenStream {
+    let set = format_ident!("set_{ident}");
+    let update = format_ident!("update_{ident}");
+    let replace = format_ident!("replace_{ident}");
+
+    let replace_setter = if matches!(MutableType::from_type(ty), Some(MutableType::Option(_))) {
+        quote! {}
+    } else {
+        quote! {
+            #vis fn #replace(&mut self, value: #ty) -> #ty {
+                std::mem::replace(&mut *self.#ident.write(), value)
+            }
+        }
+    };
+
+    quote! {
+        impl #struct_name {
+            #vis fn #set(&mut self, value: #ty) {
+                self.#ident.set(value);
+            }
+
+            #vis fn #update<O, F: FnOnce(&mut #ty) -> O>(&mut self, f: F) -> O {
+                f(&mut self.#ident.write())
+            }
+
+            #replace_setter
+        }
+    }
+}
+
+/// Implement the mutators that depend on the type of a field, like `toggle_x` for a `bool` or
+/// `push_x` for a `Vec`, with the visibility `vis`.
+pub fn impl_mutators(
+    struct_name: &Ident,
+    ident: &Ident,
+    ty: &Type,
+    vis: &Visibility,
+) -> TokenStream {
+    let Some(mutable_type) = MutableType::from_type(ty) else {
+        return quote! {};
+    };
+
+    let methods = match mutable_type {
+        MutableType::Bool => {
+            let toggle = format_ident!("toggle_{ident}");
+            quote! {
+                #vis fn #toggle(&mut self) {
+                    let mut value = self.#ident.write();
+                    *value = !*value;
+                }
+            }
+        },
+        MutableType::Integer => impl_number_mutators(ident, vis, &quote!(1)),
+        MutableType::Float => impl_number_mutators(ident, vis, &quote!(1.0)),
+        MutableType::Vec(item) => {
+            let push = format_ident!("push_{ident}");
+            let remove = format_ident!("remove_{ident}");
+            let clear = format_ident!("clear_{ident}");
+            let retain = format_ident!("retain_{ident}");
+            quote! {
+                #vis fn #push(&mut self, value: #item) {
+                    self.#ident.write().push(value);
+                }
+
+                #vis fn #remove(&mut self, index: usize) -> #item {
+                    self.#ident.write().remove(index)
+                }
+
+                #vis fn #clear(&mut self) {
+                    self.#ident.write().clear();
+                }
+
+                #vis fn #retain<F: FnMut(&#item) -> bool>(&mut self, f: F) {
+                    self.#ident.write().retain(f);
+                }
+            }
+        },
+        MutableType::Map(key, value) => {
+            let insert = format_ident!("insert_{ident}");
+            let remove = format_ident!("remove_{ident}");
+            quote! {
+                #vis fn #insert(&mut self, key: #key, value: #value) -> Option<#value> {
+                    self.#ident.write().insert(key, value)
+                }
+
+                #vis fn #remove(&mut self, key: &#key) -> Option<#value> {
+                    self.#ident.write().remove(key)
+                }
+            }
+        },
+        MutableType::Option(inner) => {
+            let take = format_ident!("take_{ident}");
+            let replace = format_ident!("replace_{ident}");
+            quote! {
+                #vis fn #take(&mut self) -> Option<#inner> {
+                    self.#ident.write().take()
+                }
+
+                #vis fn #replace(&mut self, value: #inner) -> Option<#inner> {
+                    self.#ident.write().replace(value)
+                }
+            }
+        },
+    };
+
+    quote! {
+        impl #struct_name {
+            #methods
+        }
+    }
+}
+
+fn impl_number_mutators(ident: &Ident, vis: &Visibility, one: &TokenStream) -> TokenStream {
+    let inc = format_ident!("inc_{ident}");
+    let dec = format_ident!("dec_{ident}");
+
+    quote! {
+        #vis fn #inc(&mut self) {
+            self.#ident += #one;
+        }
+
+        #vis fn #dec(&mut self) {
+            self.#ident -= #one;
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use {
+    crate::{MarkedFields, TypeOfField},
+    proc_macro2::Span,
+    syn::{
+        meta::ParseNestedMeta,
+        spanned::Spanned,
+        Attribute,
+        Expr,
+        Field,
+        Ident,
+        LitInt,
+        LitStr,
+        Path,
+        Token,
+    },
+};
+
+/// Options of a store, given with `#[modx::store(...)]`.
+#[derive(Default)]
+pub struct StoreOptions {
+    /// Don't generate the getters that clone the value of a field, for types that aren't `Clone`.
+    pub no_clone: bool,
+    /// The timer of every resource of the store, given with `timer = path::to::Timer`.
+    pub timer: Option<Path>,
+}
+
+impl StoreOptions {
+    /// Parse one option of the `store` attribute.
+    pub fn parse(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("no_clone") {
+            self.no_clone = true;
+            Ok(())
+        } else if meta.path.is_ident("timer") {
+            self.timer = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("Default") {
+            // Stores always implement `new`, but this was accepted before the options existed
+            Ok(())
+        } else {
+            Err(meta.error("unknown store option"))
+        }
+    }
+}
+
+/// Options of a field, given with `#[modx(...)]`.
+#[derive(Default)]
+pub struct FieldOptions {
+    /// The field is another store, and is kept as is instead of being wrapped in a signal.
+    pub nested: Option<Span>,
+    /// The field can be read with the visibility of the field, but only written from the module
+    /// of the store.
+    pub readonly: bool,
+    /// The field is given when the store is created, with `#[modx(props)]` or `#[modx::props]`.
+    pub props: Option<Span>,
+    /// The field is a resource, with `#[modx(resource)]` or `#[modx::resource]`.
+    pub resource: Option<Span>,
+    /// The field is filled by a stream, with `#[modx(stream)]`.
+    pub stream: Option<Span>,
+    /// The field is the messages of a coroutine, with `#[modx(coroutine)]`.
+    pub coroutine: Option<Span>,
+    /// How the resource is loaded.
+    pub resource_options: ResourceOptions,
+}
+
+/// Options of a resource field, given with `#[modx(resource, ...)]`.
+#[derive(Default, Clone)]
+pub struct ResourceOptions {
+    /// The async function that loads the resource, given with `#[modx(resource = loader)]`, or
+    /// that creates the stream with `#[modx(stream = loader)]`, or that runs the coroutine with
+    /// `#[modx(coroutine = loader)]`. The method with the same name as the field is used by
+    /// default.
+    pub loader: Option<Expr>,
+    /// How many times a failed load is retried.
+    pub retry: Option<LitInt>,
+    /// The variant of `modx::Backoff`, given as a string like `"exponential"`.
+    pub backoff: Option<Ident>,
+    pub retry_delay_ms: Option<LitInt>,
+    pub timeout_ms: Option<LitInt>,
+    /// The delay to wait before a load, so that rapid changes only load the resource once.
+    pub debounce_ms: Option<LitInt>,
+    /// The delay between two loads, given as a string like `"30s"`.
+    pub refresh_every_ms: Option<LitInt>,
+    /// The variant of `modx::Concurrency`, given as a string like `"queue"`.
+    pub concurrency: Option<Ident>,
+    /// The key of the resource in the shared cache, given as a closure that takes the store.
+    pub key: Option<Expr>,
+    /// How long a cached value stays fresh. It's stale right away by default.
+    pub ttl_ms: Option<LitInt>,
+    /// The resource loads one page at a time, with `#[modx(paginated)]`. This also marks the
+    /// field as a resource.
+    pub paginated: Option<Span>,
+    /// The `modx::Timer` of the resource. The timer of the store is used by default.
+    pub timer: Option<Path>,
+    /// The method or loader takes a `modx::CancellationToken` as its last argument, with
+    /// `#[modx(resource, token)]`.
+    pub token: Option<Span>,
+}
+
+impl FieldOptions {
+    /// Parse the `#[modx(...)]` attributes of a field, and remove them from it.
+    pub fn extract(field: &mut Field) -> syn::Result<Self> {
+        let mut options = Self::default();
+
+        for attr in field.attrs.iter().filter(|attr| is_modx_attribute(attr)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("nested") {
+                    set_marker(&mut options.nested, meta.path.span(), "nested")
+                } else if meta.path.is_ident("readonly") {
+                    options.readonly = true;
+                    Ok(())
+                } else if meta.path.is_ident("props") {
+                    set_marker(&mut options.props, meta.path.span(), "props")
+                } else if meta.path.is_ident("resource") {
+                    if meta.input.peek(Token![=]) {
+                        options.resource_options.loader = Some(meta.value()?.parse()?);
+                    }
+                    set_marker(&mut options.resource, meta.path.span(), "resource")
+                } else if meta.path.is_ident("stream") {
+                    if meta.input.peek(Token![=]) {
+                        options.resource_options.loader = Some(meta.value()?.parse()?);
+                    }
+                    set_marker(&mut options.stream, meta.path.span(), "stream")
+                } else if meta.path.is_ident("coroutine") {
+                    if meta.input.peek(Token![=]) {
+                        options.resource_options.loader = Some(meta.value()?.parse()?);
+                    }
+                    set_marker(&mut options.coroutine, meta.path.span(), "coroutine")
+                } else if meta.path.is_ident("paginated") {
+                    if meta.input.peek(Token![=]) {
+                        options.resource_options.loader = Some(meta.value()?.parse()?);
+                    }
+                    set_marker(
+                        &mut options.resource_options.paginated,
+                        meta.path.span(),
+                        "paginated",
+                    )
+                } else if options.resource_options.parse(&meta)? {
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown modx option"))
+                }
+            })?;
+        }
+
+        if let Some(nested) = options.nested &&
+            options.readonly
+        {
+            return Err(syn::Error::new(
+                nested,
+                "a nested store can't be readonly, since its handle gives write access to its \
+                 fields. Give its view instead",
+            ));
+        }
+
+        field.attrs.retain(|attr| !is_modx_attribute(attr));
+
+        Ok(options)
+    }
+
+    /// Add the markers given to the `props` and `resource` attributes that come after `store`.
+    pub fn mark(&mut self, marked_fields: &MarkedFields, ident: &Ident) -> syn::Result<()> {
+        if let Some(var) = marked_fields.props.iter().find(|var| var.ident == *ident) {
+            set_marker(&mut self.props, var.ident.span(), "props")?;
+        }
+        if let Some(var) = marked_fields
+            .resource
+            .iter()
+            .find(|var| var.ident == *ident)
+        {
+            set_marker(&mut self.resource, var.ident.span(), "resource")?;
+            self.resource_options.loader.clone_from(&var.loader);
+        }
+        Ok(())
+    }
+
+    /// What the field becomes in the store. A field can only be one of a nested store, a prop, a
+    /// resource, a stream or a coroutine.
+    pub fn type_of_field(&self) -> syn::Result<TypeOfField> {
+        // A paginated field is a resource, so it doesn't need to be marked as one
+        let resource = self.resource.or(self.resource_options.paginated);
+
+        if resource.is_none() &&
+            let Some(span) = self.resource_options.span()
+        {
+            return Err(syn::Error::new(
+                span,
+                "this option can only be given to a resource",
+            ));
+        }
+
+        if self.resource_options.key.is_none() &&
+            let Some(ttl_ms) = &self.resource_options.ttl_ms
+        {
+            return Err(syn::Error::new(
+                ttl_ms.span(),
+                "`ttl_ms` can only be given to a resource with a `key`",
+            ));
+        }
+
+        if let Some(key) = &self.resource_options.key &&
+            self.resource_options.paginated.is_some()
+        {
+            return Err(syn::Error::new(
+                key.span(),
+                "a paginated field can't be cached with a `key`, since it has several pages",
+            ));
+        }
+
+        let markers = [
+            (TypeOfField::Nested, "nested store", self.nested),
+            (TypeOfField::Props, "prop", self.props),
+            (TypeOfField::Resource, "resource", resource),
+            (TypeOfField::Stream, "stream", self.stream),
+            (TypeOfField::Coroutine, "coroutine", self.coroutine),
+        ];
+        let mut given = markers
+            .into_iter()
+            .filter_map(|(type_of_field, name, marker)| Some((type_of_field, name, marker?)));
+
+        match (given.next(), given.next()) {
+            (None, _) => Ok(TypeOfField::Signal),
+            (Some((type_of_field, ..)), None) => Ok(type_of_field),
+            (Some((TypeOfField::Nested, ..)), Some((_, _, marker))) => Err(syn::Error::new(
+                marker,
+                "a nested store is created with the `new` of the child store, so it can't be a \
+                 prop, a resource, a stream or a coroutine",
+            )),
+            (Some((_, first, _)), Some((_, second, marker))) => {
+                Err(syn::Error::new(
+                    marker,
+                    format!("a field can't be both a {first} and a {second}"),
+                ))
+            },
+        }
+    }
+}
+
+impl ResourceOptions {
+    /// Parse one option of a resource. Returns `false` if it isn't an option of a resource.
+    fn parse(&mut self, meta: &ParseNestedMeta) -> syn::Result<bool> {
+        if meta.path.is_ident("retry") {
+            self.retry = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("backoff") {
+            let backoff: LitStr = meta.value()?.parse()?;
+            let variant = match backoff.value().as_str() {
+                "fixed" => "Fixed",
+                "linear" => "Linear",
+                "exponential" => "Exponential",
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        backoff,
+                        "the backoff must be \"fixed\", \"linear\" or \"exponential\"",
+                    ));
+                },
+            };
+            self.backoff = Some(Ident::new(variant, backoff.span()));
+        } else if meta.path.is_ident("retry_delay_ms") {
+            self.retry_delay_ms = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("timeout_ms") {
+            self.timeout_ms = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("debounce_ms") {
+            self.debounce_ms = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("refresh_every") {
+            let refresh_every: LitStr = meta.value()?.parse()?;
+            let Some(ms) = parse_duration_ms(&refresh_every.value()) else {
+                return Err(syn::Error::new_spanned(
+                    refresh_every,
+                    "the delay must be a number above zero followed by `ms`, `s`, `m` or `h`, like \
+                     \"30s\"",
+                ));
+            };
+            self.refresh_every_ms = Some(LitInt::new(&ms.to_string(), refresh_every.span()));
+        } else if meta.path.is_ident("concurrency") {
+            self.concurrency = Some(parse_concurrency(meta)?);
+        } else if meta.path.is_ident("key") {
+            self.key = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("ttl_ms") {
+            self.ttl_ms = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("timer") {
+            self.timer = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("token") {
+            set_marker(&mut self.token, meta.path.span(), "token")?;
+        } else {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// The span of the first option given, other than the loader.
+    fn span(&self) -> Option<Span> {
+        self.retry
+            .as_ref()
+            .map(LitInt::span)
+            .or_else(|| self.backoff.as_ref().map(Ident::span))
+            .or_else(|| self.retry_delay_ms.as_ref().map(LitInt::span))
+            .or_else(|| self.timeout_ms.as_ref().map(LitInt::span))
+            .or_else(|| self.debounce_ms.as_ref().map(LitInt::span))
+            .or_else(|| self.refresh_every_ms.as_ref().map(LitInt::span))
+            .or_else(|| self.concurrency.as_ref().map(Ident::span))
+            .or_else(|| self.key.as_ref().map(Spanned::span))
+            .or_else(|| self.ttl_ms.as_ref().map(LitInt::span))
+            .or_else(|| self.timer.as_ref().map(Spanned::span))
+            .or(self.token)
+    }
+}
+
+/// Parse the variant of `modx::Concurrency` given as a string, like `concurrency = "latest"`.
+pub fn parse_concurrency(meta: &ParseNestedMeta) -> syn::Result<Ident> {
+    let concurrency: LitStr = meta.value()?.parse()?;
+    let variant = match concurrency.value().as_str() {
+        "latest" => "Latest",
+        "queue" => "Queue",
+        "drop_new" => "DropNew",
+        "parallel" => "Parallel",
+        _ => {
+            return Err(syn::Error::new_spanned(
+                concurrency,
+                "the concurrency must be \"latest\", \"queue\", \"drop_new\" or \"parallel\"",
+            ));
+        },
+    };
+    Ok(Ident::new(variant, concurrency.span()))
+}
+
+/// Parse a duration like `"500ms"`, `"30s"`, `"5m"` or `"1h"` into milliseconds. A duration of zero
+/// is rejected, since refreshing that often would never let the loop wait.
+fn parse_duration_ms(duration: &str) -> Option<u64> {
+    let (number, unit_ms) = if let Some(ms) = duration.strip_suffix("ms") {
+        (ms, 1)
+    } else if let Some(s) = duration.strip_suffix('s') {
+        (s, 1000)
+    } else if let Some(m) = duration.strip_suffix('m') {
+        (m, 60 * 1000)
+    } else if let Some(h) = duration.strip_suffix('h') {
+        (h, 60 * 60 * 1000)
+    } else {
+        return None;
+    };
+
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(unit_ms)
+        .filter(|ms| *ms > 0)
+}
+
+/// Mark a field with the option `name`, at the place where it was given.
+fn set_marker(marker: &mut Option<Span>, span: Span, name: &str) -> syn::Result<()> {
+    if marker.replace(span).is_some() {
+        return Err(syn::Error::new(
+            span,
+            format!("this field is already marked as `{name}`"),
+        ));
+    }
+    Ok(())
+}
+
+fn is_modx_attribute(attr: &Attribute) -> bool {
+    attr.path().is_ident("modx")
+}
@@ -0,0 +1,476 @@
+use {
+    crate::{most_restrictive_write_vis, options::ResourceOptions, StoreField},
+    proc_macro2::TokenStream,
+    quote::{format_ident, quote, quote_spanned},
+    syn::{
+        parse_quote,
+        parse_quote_spanned,
+        spanned::Spanned,
+        Field,
+        GenericArgument,
+        Ident,
+        Path,
+        PathArguments,
+        Type,
+    },
+};
+
+/// Implement the helpers of a resource field, to read its state without matching on its value.
+///
+/// The methods that read the resource have the visibility of the field, and the ones that restart
+/// or clear it have its write visibility. The getters that clone the value aren't generated
+/// with `no_clone`, and can only be called when the value is `Clone`.
+pub fn impl_resource_helpers(
+    struct_name: &Ident,
+    store_field: &StoreField,
+    no_clone: bool,
+) -> TokenStream {
+    let StoreField {
+        ident,
+        ty,
+        read_vis,
+        write_vis,
+        resource_options,
+        ..
+    } = store_field;
+    let value = format_ident!("{ident}_value");
+    let suspend = format_ident!("{ident}_suspend");
+    let is_loading = format_ident!("{ident}_is_loading");
+    let state = format_ident!("{ident}_state");
+    let reload = format_ident!("reload_{ident}");
+    let clear = format_ident!("clear_{ident}");
+
+    let refresh_helpers = resource_options.refresh_every_ms.as_ref().map(|_| {
+        let refresh = refresh_ident(ident);
+        let pause_refresh = format_ident!("pause_refresh_{ident}");
+        let resume_refresh = format_ident!("resume_refresh_{ident}");
+        quote! {
+            #write_vis fn #pause_refresh(&mut self) {
+                self.#refresh.set_paused(true);
+            }
+
+            #write_vis fn #resume_refresh(&mut self) {
+                self.#refresh.set_paused(false);
+            }
+        }
+    });
+
+    // The helpers that read the resource tell its refresh that it's still read
+    let mark_read = resource_options.refresh_every_ms.as_ref().map(|_| {
+        let refresh = refresh_ident(ident);
+        quote! { self.#refresh.mark_read(); }
+    });
+
+    let page_helpers = resource_options.paginated.as_ref().map(|_| {
+        let pages = pages_ident(ident);
+        let items = format_ident!("{ident}_items");
+        let has_more = format_ident!("{ident}_has_more");
+        let is_loading_more = format_ident!("{ident}_is_loading_more");
+        let load_more = format_ident!("load_more_{ident}");
+        let reset = format_ident!("reset_{ident}");
+        quote! {
+            #read_vis fn #items(&self) -> Vec<<#ty as ::modx::PageResult>::Item> {
+                #mark_read
+                self.#pages.items()
+            }
+
+            #read_vis fn #has_more(&self) -> bool {
+                #mark_read
+                self.#pages.has_more()
+            }
+
+            #read_vis fn #is_loading_more(&self) -> bool {
+                #mark_read
+                self.#pages.is_loading_more()
+            }
+
+            #write_vis fn #load_more(&mut self) {
+                self.#pages.load_more(self.#ident);
+            }
+
+            #write_vis fn #reset(&mut self) {
+                self.#pages.reset(self.#ident);
+            }
+        }
+    });
+
+    let invalidate_helper = resource_options.key.as_ref().map(|key| {
+        let invalidate = format_ident!("invalidate_{ident}");
+        quote! {
+            #write_vis fn #invalidate(&self) {
+                ::modx::invalidate(&::modx::key_of(self, #key));
+            }
+        }
+    });
+
+    let getters = if no_clone {
+        quote! {}
+    } else {
+        let result_getters = result_types(ty).map(|(ok_ty, err_ty)| {
+            let ok = format_ident!("{ident}_ok");
+            let err = format_ident!("{ident}_err");
+            let throw = format_ident!("{ident}_throw");
+            quote! {
+                #read_vis fn #throw(&self) -> ::core::result::Result<#ok_ty, RenderError>
+                where
+                    for<'a> #ok_ty: Clone,
+                    for<'a> #err_ty: ::core::fmt::Display,
+                {
+                    #mark_read
+                    match &*self.#ident.suspend()?.read() {
+                        ::core::result::Result::Ok(value) => ::core::result::Result::Ok(value.clone()),
+                        ::core::result::Result::Err(error) => {
+                            ::core::result::Result::Err(::modx::render_error(error))
+                        },
+                    }
+                }
+
+                #read_vis fn #ok(&self) -> Option<#ok_ty> where for<'a> #ok_ty: Clone {
+                    #mark_read
+                    self.#ident.read().as_ref().and_then(|result| result.as_ref().ok()).cloned()
+                }
+
+                #read_vis fn #err(&self) -> Option<#err_ty> where for<'a> #err_ty: Clone {
+                    #mark_read
+                    self.#ident.read().as_ref().and_then(|result| result.as_ref().err()).cloned()
+                }
+            }
+        });
+
+        // Errors are often not `Clone`, so the bounds are only checked when the getters are used.
+        // The unused lifetime keeps the bound from being checked where the getter is declared
+        quote! {
+            #read_vis fn #value(&self) -> Option<#ty> where for<'a> #ty: Clone {
+                #mark_read
+                self.#ident.read().clone()
+            }
+
+            #result_getters
+        }
+    };
+
+    quote! {
+        impl #struct_name {
+            #getters
+
+            #read_vis fn #suspend(&self) -> ::core::result::Result<MappedSignal<#ty>, RenderError> {
+                #mark_read
+                self.#ident.suspend()
+            }
+
+            #read_vis fn #is_loading(&self) -> bool {
+                #mark_read
+                *self.#ident.state().read() == UseResourceState::Pending
+            }
+
+            #read_vis fn #state(&self) -> UseResourceState {
+                #mark_read
+                *self.#ident.state().read()
+            }
+
+            #write_vis fn #reload(&mut self) {
+                self.#ident.restart();
+            }
+
+            #write_vis fn #clear(&mut self) {
+                self.#ident.clear();
+            }
+
+            #refresh_helpers
+            #page_helpers
+            #invalidate_helper
+        }
+    }
+}
+
+/// The types `T` and `E` of a `Result<T, E>`. Aliases like `io::Result<T>` are not detected.
+fn result_types(ty: &Type) -> Option<(&Type, &Type)> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    let mut types = args.args.iter().filter_map(|arg| {
+        match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }
+    });
+
+    match (types.next(), types.next(), types.next()) {
+        (Some(ok_ty), Some(err_ty), None) => Some((ok_ty, err_ty)),
+        _ => None,
+    }
+}
+
+/// Create a resource field of a store in `new`, once the rest of the store is created, since the
+/// resource needs the store to be loaded.
+///
+/// The timer of the field is used if there is one, else the timer of the store `store_timer`,
+/// else `modx::ThreadTimer`.
+pub fn new_resource(store_field: &StoreField, store_timer: Option<&Path>) -> TokenStream {
+    let StoreField {
+        ident,
+        ty,
+        resource_options,
+        ..
+    } = store_field;
+    let ResourceOptions {
+        loader,
+        retry,
+        backoff,
+        retry_delay_ms,
+        timeout_ms,
+        debounce_ms,
+        refresh_every_ms,
+        concurrency,
+        key,
+        ttl_ms,
+        paginated,
+        timer,
+        token,
+    } = resource_options;
+    let timer = timer
+        .as_ref()
+        .or(store_timer)
+        .map_or_else(|| quote! { ::modx::ThreadTimer }, |timer| quote! { #timer });
+
+    // The loader is checked with its own span, so that a wrong signature is reported there instead
+    // of inside the macro
+    // A paginated field also gives the cursor of the page, and the token comes last, like for an
+    // action
+    let pages = pages_ident(ident);
+    let cursor = paginated.as_ref().map(|_| quote! { cursor });
+    let token_arg = token.map(|token| quote_spanned! { token=> token });
+    let args = cursor.iter().chain(&token_arg);
+    let mut load = match loader {
+        Some(loader) => {
+            quote_spanned! { loader.span()=>
+                ::modx::resource_future::<#ty, _>((#loader)(&mut default_struct #(, #args)*)).await
+            }
+        },
+        None => {
+            quote_spanned! { ident.span()=>
+                ::modx::resource_future::<#ty, _>(default_struct.#ident(#(#args),*)).await
+            }
+        },
+    };
+    if paginated.is_some() {
+        load = quote! {{
+            let cursor = default_struct.#pages.cursor();
+            #load
+        }};
+    }
+
+    if let Some(timeout_ms) = timeout_ms {
+        load = quote_spanned! { timeout_ms.span()=>
+            ::modx::timeout::<#timer, #ty, _>(
+                std::time::Duration::from_millis(#timeout_ms),
+                async { #load },
+            )
+            .await
+        };
+    }
+
+    if let Some(retry) = retry {
+        let backoff = backoff
+            .as_ref()
+            .map(|backoff| quote! { backoff: ::modx::Backoff::#backoff, });
+        let delay = retry_delay_ms
+            .as_ref()
+            .map(|delay| quote! { delay: std::time::Duration::from_millis(#delay), });
+        // Each attempt gets its own copy of the token
+        let clone_token = token
+            .as_ref()
+            .map(|_| quote! { let token = ::core::clone::Clone::clone(&token); });
+        load = quote_spanned! { retry.span()=>
+            ::modx::retry::<#timer, #ty, _, _>(
+                ::modx::RetryPolicy {
+                    retries: #retry,
+                    #backoff
+                    #delay
+                    ..::core::default::Default::default()
+                },
+                move || {
+                    #clone_token
+                    async move { #load }
+                },
+            )
+            .await
+        };
+    }
+
+    // The value is shared by every resource with the same key, and the key is read inside the
+    // resource so that it's reloaded when the key changes
+    let user = key
+        .as_ref()
+        .map(|_| quote! { let user = use_hook(|| CopyValue::new(::modx::CacheUser::new())); });
+    if let Some(key) = key {
+        let ttl_ms = ttl_ms
+            .as_ref()
+            .map_or_else(|| quote! { 0 }, |ttl_ms| quote! { #ttl_ms });
+        load = quote_spanned! { key.span()=>
+            ::modx::query::<#timer, _, #ty, _, _>(
+                ::modx::key_of(&default_struct, #key),
+                std::time::Duration::from_millis(#ttl_ms),
+                user.cloned(),
+                move || async move { #load },
+            )
+            .await
+        };
+    }
+
+    // The load runs in its own task, so that it isn't cancelled when the resource restarts, unless
+    // the policy says so, and its token is cancelled along with it. Without a policy, the token is
+    // cancelled when the resource restarts
+    let calls = concurrency.as_ref().map(|_| {
+        quote! { let calls = use_hook(|| CopyValue::new(::modx::Calls::default())); }
+    });
+    let token_pat = token_arg.unwrap_or_else(|| quote! { _ });
+    if let Some(concurrency) = concurrency {
+        load = quote_spanned! { concurrency.span()=>
+            ::modx::load_concurrently(
+                calls.cloned(),
+                ::modx::Concurrency::#concurrency,
+                move |#token_pat: ::modx::CancellationToken| async move { #load },
+            )
+            .await
+        };
+    } else if let Some(token) = token {
+        load = quote_spanned! { *token=>
+            ::modx::load_cancellable(move |token: ::modx::CancellationToken| async move { #load })
+                .await
+        };
+    }
+
+    if let Some(paginated) = paginated {
+        load = quote_spanned! { *paginated=>
+            default_struct.#pages.load(move || async move { #load }).await
+        };
+    }
+
+    // A change while waiting restarts the resource, which cancels this load
+    let debounce = debounce_ms.as_ref().map(|debounce_ms| {
+        quote! {
+            <#timer as ::modx::Timer>::sleep(std::time::Duration::from_millis(#debounce_ms)).await;
+        }
+    });
+
+    let refresh = refresh_every_ms.as_ref().map(|refresh_every_ms| {
+        let refresh = refresh_ident(ident);
+        quote! {
+            use_hook(move || {
+                spawn(::modx::refresh::<#timer, _>(
+                    std::time::Duration::from_millis(#refresh_every_ms),
+                    default_struct.#ident,
+                    default_struct.#refresh,
+                ))
+            });
+        }
+    });
+
+    quote! {
+        default_struct.#ident = {
+            #user
+            #calls
+            use_resource(move || async move {
+                #debounce
+                #load
+            })
+        };
+        #refresh
+    }
+}
+
+/// The value of a resource field in the literal of the store, and of the hidden fields that pause
+/// its refresh and keep its pages.
+///
+/// The resource needs the store, so this placeholder, that never resolves, is replaced by
+/// [`new_resource`] just after the store is created.
+pub fn default_resource(store_field: &StoreField) -> TokenStream {
+    let StoreField {
+        ident,
+        resource_options,
+        ..
+    } = store_field;
+
+    let refresh = resource_options.refresh_every_ms.as_ref().map(|_| {
+        let refresh = refresh_ident(ident);
+        quote! { #refresh: use_hook(::modx::Refresh::new), }
+    });
+    let pages = resource_options.paginated.as_ref().map(|_| {
+        let pages = pages_ident(ident);
+        quote! { #pages: use_hook(::modx::Pages::new), }
+    });
+
+    quote! {
+        #ident: use_resource(std::future::pending),
+        #refresh
+        #pages
+    }
+}
+
+/// The hidden field of the store that pauses the refresh of a resource, and knows if it's still
+/// read, if it has `refresh_every`.
+pub fn refresh_field(store_field: &StoreField) -> Option<Field> {
+    store_field.resource_options.refresh_every_ms.as_ref()?;
+
+    let refresh = refresh_ident(&store_field.ident);
+    Some(parse_quote! { #refresh: ::modx::Refresh })
+}
+
+/// The hidden field of the store that keeps the pages of a resource, if it's `paginated`.
+pub fn pages_field(store_field: &StoreField) -> Option<Field> {
+    store_field.resource_options.paginated.as_ref()?;
+
+    let pages = pages_ident(&store_field.ident);
+    let ty = &store_field.ty;
+    Some(parse_quote_spanned! { ty.span()=> #pages: ::modx::Pages<#ty> })
+}
+
+/// Implement `pause_refresh` and `resume_refresh`, that pause the refresh of every resource of the
+/// store, if some have `refresh_every`.
+///
+/// Like the methods of each resource, they can only be called where every resource can be written.
+pub fn impl_refresh_all(struct_name: &Ident, all_idents_types: &[StoreField]) -> TokenStream {
+    let fields = all_idents_types
+        .iter()
+        .filter(|store_field| store_field.resource_options.refresh_every_ms.is_some())
+        .collect::<Vec<_>>();
+
+    if fields.is_empty() {
+        return quote! {};
+    }
+
+    let refreshes = fields
+        .iter()
+        .map(|StoreField { ident, .. }| refresh_ident(ident))
+        .collect::<Vec<_>>();
+    let write_vis = most_restrictive_write_vis(&fields);
+
+    quote! {
+        impl #struct_name {
+            #write_vis fn pause_refresh(&mut self) {
+                #(self.#refreshes.set_paused(true);)*
+            }
+
+            #write_vis fn resume_refresh(&mut self) {
+                #(self.#refreshes.set_paused(false);)*
+            }
+        }
+    }
+}
+
+fn refresh_ident(ident: &Ident) -> Ident {
+    format_ident!("_modx_refresh_{ident}")
+}
+
+fn pages_ident(ident: &Ident) -> Ident {
+    format_ident!("_modx_pages_{ident}")
+}
@@ -0,0 +1,158 @@
+use {
+    crate::{initial_ident, StoreField, TypeOfField},
+    proc_macro2::TokenStream,
+    quote::quote,
+    syn::Ident,
+};
+
+/// Implement `modx::Snapshot`, and `modx::FromSnapshot` for the stores that can be created
+/// outside of hooks.
+///
+/// The snapshot is a tuple with the value of every signal and the snapshot of every nested field.
+/// Resources are loaded again instead, streams are only written by their stream, and coroutines
+/// have no value, so they are left out. Like the cloning getters, the bounds are only checked when
+/// the snapshot is used, so that stores with fields that aren't `Clone` can still be declared.
+///
+/// `restore` leaves the `readonly` fields alone, since it can be called outside of the module of
+/// the store. A failed optimistic action restores them with `_modx_restore` instead, which is
+/// private to the module.
+///
+/// A store created from a snapshot takes the values of the snapshot as its initial values.
+pub fn impl_snapshot(
+    struct_name: &Ident,
+    all_idents_types: &[StoreField],
+    no_clone: bool,
+) -> TokenStream {
+    let fields = all_idents_types
+        .iter()
+        .filter(|StoreField { type_of_field, .. }| {
+            !matches!(
+                type_of_field,
+                TypeOfField::Resource | TypeOfField::Stream | TypeOfField::Coroutine
+            )
+        })
+        .collect::<Vec<_>>();
+    let idents = fields
+        .iter()
+        .map(|StoreField { ident, .. }| ident)
+        .collect::<Vec<_>>();
+
+    let mut bounds = vec![];
+    let mut snapshot_types = vec![];
+    let mut snapshots = vec![];
+    let mut from_snapshots = vec![];
+    for StoreField {
+        ident,
+        ty,
+        type_of_field,
+        ..
+    } in &fields
+    {
+        if *type_of_field == TypeOfField::Nested {
+            bounds.push(quote! { for<'a> #ty: ::modx::FromSnapshot });
+            snapshot_types.push(quote! { <#ty as ::modx::Snapshot>::Snapshot });
+            snapshots.push(quote! { ::modx::Snapshot::snapshot(&self.#ident) });
+            from_snapshots.push(quote! {
+                #ident: <#ty as ::modx::FromSnapshot>::from_snapshot(owner, #ident),
+            });
+        } else {
+            bounds.push(quote! { for<'a> #ty: Clone });
+            snapshot_types.push(quote! { #ty });
+            snapshots.push(quote! { ::core::clone::Clone::clone(&*self.#ident.peek()) });
+            if !no_clone {
+                let initial = initial_ident(ident);
+                from_snapshots.push(quote! {
+                    #initial: Signal::new_in_scope(::core::clone::Clone::clone(&#ident), owner),
+                });
+            }
+            from_snapshots.push(quote! { #ident: Signal::new_in_scope(#ident, owner), });
+        }
+    }
+
+    let restores = fields.iter().copied().map(restore_outside_module);
+    let impl_private_restore = impl_private_restore(struct_name, &fields, &bounds);
+
+    let needs_hooks = fields.len() != all_idents_types.len();
+    let impl_from_snapshot = if needs_hooks {
+        quote! {}
+    } else {
+        quote! {
+            impl ::modx::FromSnapshot for #struct_name where #(#bounds,)* {
+                fn from_snapshot(owner: ScopeId, snapshot: Self::Snapshot) -> Self {
+                    let (#(#idents,)*) = snapshot;
+                    #struct_name {
+                        #(#from_snapshots)*
+                    }
+                }
+            }
+        }
+    };
+
+    quote! {
+        impl ::modx::Snapshot for #struct_name where #(#bounds,)* {
+            type Snapshot = (#(#snapshot_types,)*);
+
+            fn snapshot(&self) -> Self::Snapshot {
+                (#(#snapshots,)*)
+            }
+
+            fn restore(&mut self, snapshot: Self::Snapshot) {
+                let (#(#idents,)*) = snapshot;
+                #(#restores)*
+            }
+        }
+
+        #impl_private_restore
+
+        #impl_from_snapshot
+    }
+}
+
+/// Implement `_modx_restore`, that writes back every value of a snapshot, `readonly` fields
+/// included. It's private to the module of the store, like the setters of those fields.
+fn impl_private_restore(
+    struct_name: &Ident,
+    fields: &[&StoreField],
+    bounds: &[TokenStream],
+) -> TokenStream {
+    let idents = fields.iter().map(|StoreField { ident, .. }| ident);
+    let restores = fields.iter().copied().map(restore_field);
+
+    quote! {
+        impl #struct_name {
+            #[allow(dead_code)]
+            fn _modx_restore(&mut self, snapshot: <Self as ::modx::Snapshot>::Snapshot)
+            where
+                #(#bounds,)*
+            {
+                let (#(#idents,)*) = snapshot;
+                #(#restores)*
+            }
+        }
+    }
+}
+
+/// Write back the value of one field, named like the field.
+fn restore_field(
+    StoreField {
+        ident,
+        type_of_field,
+        ..
+    }: &StoreField,
+) -> TokenStream {
+    if *type_of_field == TypeOfField::Nested {
+        quote! { ::modx::Snapshot::restore(&mut self.#ident, #ident); }
+    } else {
+        quote! { self.#ident.set(#ident); }
+    }
+}
+
+/// Write back the value of one field, unless it's `readonly`.
+fn restore_outside_module(field: &StoreField) -> TokenStream {
+    if field.readonly() {
+        let ident = &field.ident;
+        quote! { let _ = #ident; }
+    } else {
+        restore_field(field)
+    }
+}
@@ -0,0 +1,128 @@
+use {
+    crate::{impl_accessors, StoreField},
+    proc_macro2::TokenStream,
+    quote::{format_ident, quote, quote_spanned},
+    syn::{parse_quote, spanned::Spanned, Field, Ident},
+};
+
+/// Implement the accessors and helpers of a stream field.
+///
+/// The method with the name of the field creates the stream, so the getter that clones the value
+/// is `field_value()`. The value is only written by the stream, so there is no setter.
+pub fn impl_stream_helpers(
+    struct_name: &Ident,
+    store_field: &StoreField,
+    no_clone: bool,
+) -> TokenStream {
+    let StoreField {
+        ident,
+        ty,
+        read_vis,
+        write_vis,
+        ..
+    } = store_field;
+    let task = task_ident(ident);
+    let stream_state = state_ident(ident);
+    let value = format_ident!("{ident}_value");
+    let state = format_ident!("{ident}_state");
+    let is_done = format_ident!("{ident}_is_done");
+    let error = format_ident!("{ident}_error");
+    let restart = format_ident!("restart_{ident}");
+
+    let accessors = impl_accessors(struct_name, ident, ty, read_vis, true);
+    let getter = if no_clone {
+        quote! {}
+    } else {
+        quote! {
+            #read_vis fn #value(&self) -> #ty where for<'a> #ty: Clone {
+                self.#ident.read().clone()
+            }
+        }
+    };
+
+    quote! {
+        #accessors
+
+        impl #struct_name {
+            #getter
+
+            #read_vis fn #state(&self) -> ::modx::StreamState {
+                self.#stream_state.read().clone()
+            }
+
+            #read_vis fn #is_done(&self) -> bool {
+                *self.#stream_state.read() == ::modx::StreamState::Done
+            }
+
+            #read_vis fn #error(&self) -> Option<::dioxus::CapturedError> {
+                match &*self.#stream_state.read() {
+                    ::modx::StreamState::Failed(error) => Some(error.clone()),
+                    _ => None,
+                }
+            }
+
+            #write_vis fn #restart(&mut self) {
+                self.#task.restart();
+            }
+        }
+    }
+}
+
+/// The value of a stream field in the literal of the store, and of its hidden fields.
+///
+/// The task that reads the stream needs the store, so this placeholder is replaced by
+/// [`new_stream`] just after the store is created.
+pub fn default_stream(store_field: &StoreField, value: &TokenStream) -> TokenStream {
+    let ident = &store_field.ident;
+    let task = task_ident(ident);
+    let state = state_ident(ident);
+
+    quote! {
+        #ident: use_signal(|| #value),
+        #task: use_resource(std::future::pending),
+        #state: use_signal(::modx::StreamState::default),
+    }
+}
+
+/// Start reading the stream of a field in `new`, once the rest of the store is created.
+///
+/// The stream is read in a resource, so that it's created again when a signal read by its method
+/// changes.
+pub fn new_stream(store_field: &StoreField) -> TokenStream {
+    let StoreField {
+        ident,
+        resource_options,
+        ..
+    } = store_field;
+    let task = task_ident(ident);
+    let state = state_ident(ident);
+
+    let stream = match &resource_options.loader {
+        Some(loader) => quote_spanned! { loader.span()=> (#loader)(&mut default_struct) },
+        None => quote_spanned! { ident.span()=> default_struct.#ident() },
+    };
+
+    quote! {
+        default_struct.#task = use_resource(move || async move {
+            ::modx::collect_stream(default_struct.#ident, default_struct.#state, #stream).await;
+        });
+    }
+}
+
+/// The hidden fields of the store that read the stream of a field, and keep its state.
+pub fn stream_fields(store_field: &StoreField) -> [Field; 2] {
+    let task = task_ident(&store_field.ident);
+    let state = state_ident(&store_field.ident);
+    [
+        parse_quote! { #task: Resource<()> },
+        parse_quote! { #state: Signal<::modx::StreamState> },
+    ]
+}
+
+fn task_ident(ident: &Ident) -> Ident {
+    format_ident!("_modx_stream_{ident}")
+}
+
+fn state_ident(ident: &Ident) -> Ident {
+    format_ident!("_modx_stream_state_{ident}")
+}
@@ -0,0 +1,103 @@
+use {
+    crate::{impl_props, StoreField, TypeOfField},
+    proc_macro2::TokenStream,
+    quote::{format_ident, quote},
+    syn::{Ident, Visibility},
+};
+
+/// Implement the view of a store: a copy of the store where every field is read-only, that is
+/// created with `store.view()`.
+///
+/// Nested stores are not part of the view, since they would give write access to their fields, and
+/// neither are coroutines, since they have no value to read.
+pub fn impl_view(
+    struct_name: &Ident,
+    struct_visibility: &Visibility,
+    all_idents_types: &[StoreField],
+    no_clone: bool,
+) -> TokenStream {
+    let view_name = format_ident!("{struct_name}View");
+    let viewed_fields = all_idents_types
+        .iter()
+        .filter(|StoreField { type_of_field, .. }| {
+            !matches!(type_of_field, TypeOfField::Nested | TypeOfField::Coroutine)
+        })
+        .collect::<Vec<_>>();
+
+    let fields = viewed_fields.iter().map(
+        |StoreField {
+             ident,
+             ty,
+             read_vis,
+             type_of_field,
+             ..
+         }| {
+            match type_of_field {
+                TypeOfField::Resource => quote! { #read_vis #ident: ReadOnlySignal<Option<#ty>>, },
+                _ => quote! { #read_vis #ident: ReadOnlySignal<#ty>, },
+            }
+        },
+    );
+
+    let field_inits = viewed_fields.iter().map(
+        |StoreField {
+             ident,
+             type_of_field,
+             ..
+         }| {
+            match type_of_field {
+                TypeOfField::Resource => quote! { #ident: self.#ident.value(), },
+                _ => quote! { #ident: ReadOnlySignal::new(self.#ident), },
+            }
+        },
+    );
+
+    let getters = viewed_fields
+        .iter()
+        .filter(|_| !no_clone)
+        .filter(|StoreField { type_of_field, .. }| *type_of_field != TypeOfField::Resource)
+        .map(
+            |StoreField {
+                 ident,
+                 ty,
+                 read_vis,
+                 ..
+             }| {
+                quote! {
+                    #read_vis fn #ident(&self) -> #ty {
+                        self.#ident.read().clone()
+                    }
+                }
+            },
+        );
+
+    let impl_props = impl_props(
+        &view_name,
+        struct_visibility,
+        viewed_fields.iter().map(|StoreField { ident, .. }| ident),
+        &format_ident!("view"),
+    );
+
+    quote! {
+        /// A read-only view of the store.
+        #[derive(Copy, Clone)]
+        #struct_visibility struct #view_name {
+            #(#fields)*
+        }
+
+        impl #struct_name {
+            /// Create a read-only view of the store, that can be given to other components.
+            pub fn view(&self) -> #view_name {
+                #view_name {
+                    #(#field_inits)*
+                }
+            }
+        }
+
+        impl #view_name {
+            #(#getters)*
+        }
+
+        #impl_props
+    }
+}
@@ -0,0 +1,7 @@
+#[modx::props(name, name)]
+#[modx::store]
+struct UserStore {
+    name: String,
+}
+
+fn main() {}
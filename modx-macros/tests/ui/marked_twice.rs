@@ -0,0 +1,8 @@
+#[modx::props(name)]
+#[modx::store]
+struct UserStore {
+    #[modx(props)]
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use dioxus::prelude::*;
+
+#[modx::store]
+struct TodoStore {
+    todos: Vec<String>,
+}
+
+impl TodoStore {
+    #[modx::action(optimistic, concurrency = "parallel")]
+    async fn add(&mut self, todo: String) -> Result<(), String> {
+        self.todos.write().push(todo);
+        Ok(())
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+#[modx::store]
+struct UserStore {
+    #[modx(props, resource)]
+    name: String,
+}
+
+impl UserStore {
+    async fn name(&mut self) -> String {
+        String::new()
+    }
+}
+
+fn main() {}
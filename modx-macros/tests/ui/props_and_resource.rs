@@ -0,0 +1,14 @@
+#[modx::props(name)]
+#[modx::resource(name)]
+#[modx::store]
+struct UserStore {
+    name: String,
+}
+
+impl UserStore {
+    async fn name(&mut self) -> String {
+        String::new()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+#[modx::store]
+struct WeatherStore {
+    #[modx(resource, refresh_every = "0s")]
+    weather: String,
+}
+
+impl WeatherStore {
+    async fn weather(&mut self) -> String {
+        String::new()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use dioxus::prelude::*;
+
+#[modx::store]
+struct CatStore {
+    #[modx(resource)]
+    cat: String,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use dioxus::prelude::*;
+
+#[modx::store]
+struct CatStore {
+    #[modx(resource)]
+    cat: String,
+}
+
+impl CatStore {
+    fn cat(&mut self) -> String {
+        String::new()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use dioxus::prelude::*;
+
+#[modx::store]
+struct CatStore {
+    #[modx(resource)]
+    cat: String,
+}
+
+impl CatStore {
+    async fn cat(&mut self, id: u32) -> String {
+        id.to_string()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use dioxus::prelude::*;
+
+#[modx::store]
+struct CatStore {
+    #[modx(resource, token)]
+    cat: String,
+}
+
+impl CatStore {
+    async fn cat(&mut self) -> String {
+        String::new()
+    }
+}
+
+fn main() {}
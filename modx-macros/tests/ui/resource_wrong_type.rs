@@ -0,0 +1,15 @@
+use dioxus::prelude::*;
+
+#[modx::store]
+struct CatStore {
+    #[modx(resource)]
+    cat: String,
+}
+
+impl CatStore {
+    async fn cat(&mut self) -> u32 {
+        0
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+#[modx::resource(fetch_catt)]
+#[modx::store]
+struct CatStore {
+    fetch_cat: String,
+}
+
+impl CatStore {
+    async fn fetch_cat(&mut self) -> String {
+        String::new()
+    }
+}
+
+fn main() {}
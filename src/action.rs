@@ -0,0 +1,585 @@
+use {
+    crate::{spawn_concurrently, Calls, CancellationToken, Concurrency, Snapshot, Timer},
+    dioxus::prelude::spawn,
+    std::{
+        any::Any,
+        cell::{Cell, RefCell},
+        future::Future,
+        task::{Context, Poll, Waker},
+        thread::LocalKey,
+        time::Duration,
+    },
+};
+
+/// The calls of an action that are waiting, for every store that called it.
+///
+/// This is only used by the `action` macro, which declares one for each action.
+#[doc(hidden)]
+pub struct ActionState {
+    /// The stores that called the action, along with the number of their last call.
+    calls: RefCell<Vec<(Box<dyn Any>, u64)>>,
+}
+
+impl ActionState {
+    pub const fn new() -> Self {
+        Self {
+            calls: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Count a new call of the action by `store`, and return its number.
+    fn call<S: PartialEq + 'static>(&self, store: S) -> u64 {
+        let mut calls = self.calls.borrow_mut();
+        if let Some((_, call)) = calls
+            .iter_mut()
+            .find(|(other, _)| other.downcast_ref::<S>() == Some(&store))
+        {
+            *call += 1;
+            *call
+        } else {
+            calls.push((Box::new(store), 0));
+            0
+        }
+    }
+
+    /// If `store` has a call waiting.
+    fn is_waiting<S: PartialEq + 'static>(&self, store: &S) -> bool {
+        self.calls
+            .borrow()
+            .iter()
+            .any(|(other, _)| other.downcast_ref::<S>() == Some(store))
+    }
+
+    /// Forget the calls of `store` if `call` is its last one, and return if it was.
+    fn finish<S: PartialEq + 'static>(&self, store: &S, call: u64) -> bool {
+        let mut calls = self.calls.borrow_mut();
+        let Some(index) = calls.iter().position(|(other, last_call)| {
+            other.downcast_ref::<S>() == Some(store) && *last_call == call
+        }) else {
+            return false;
+        };
+
+        calls.remove(index);
+        true
+    }
+}
+
+impl Default for ActionState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The value returned by an optimistic action, that tells if the store needs to be restored.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(message = "an optimistic action must return a `Result`")]
+pub trait OptimisticResult {
+    fn is_failure(&self) -> bool;
+}
+
+impl<T, E> OptimisticResult for Result<T, E> {
+    fn is_failure(&self) -> bool {
+        self.is_err()
+    }
+}
+
+/// The calls of an optimistic action that aren't finished, for every store that made them.
+///
+/// This is only used by the `action` macro, which declares one for each optimistic action.
+#[doc(hidden)]
+pub struct OptimisticCalls {
+    /// The calls, in the order they were made.
+    calls: RefCell<Vec<OptimisticCall>>,
+    last_id: Cell<u64>,
+}
+
+struct OptimisticCall {
+    store: Box<dyn Any>,
+    id: u64,
+    /// Cancelled when an earlier call of the store fails, which undoes this one.
+    token: CancellationToken,
+}
+
+impl OptimisticCalls {
+    pub const fn new() -> Self {
+        Self {
+            calls: RefCell::new(Vec::new()),
+            last_id: Cell::new(0),
+        }
+    }
+
+    /// Count a new call by `store`, and return its number.
+    fn start<S: Copy + 'static>(&self, store: S, token: CancellationToken) -> u64 {
+        let id = self.last_id.get() + 1;
+        self.last_id.set(id);
+        self.calls.borrow_mut().push(OptimisticCall {
+            store: Box::new(store),
+            id,
+            token,
+        });
+        id
+    }
+
+    /// Cancel the calls of `store` made after the call `id`, since restoring the store before
+    /// this one also undid their changes.
+    fn undo_after<S: PartialEq + 'static>(&self, store: &S, id: u64) {
+        let undone = self
+            .calls
+            .borrow()
+            .iter()
+            .filter(|call| call.id > id && call.store.downcast_ref::<S>() == Some(store))
+            .map(|call| call.token.clone())
+            .collect::<Vec<_>>();
+        undone.iter().for_each(CancellationToken::cancel);
+    }
+
+    fn finish(&self, id: u64) {
+        self.calls.borrow_mut().retain(|call| call.id != id);
+    }
+}
+
+impl Default for OptimisticCalls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An optimistic call that isn't finished, that is forgotten even if its task is dropped.
+struct OptimisticGuard {
+    state: &'static LocalKey<OptimisticCalls>,
+    id: u64,
+}
+
+impl Drop for OptimisticGuard {
+    fn drop(&mut self) {
+        // The state is already gone if the task is dropped along with the thread
+        let _ = self.state.try_with(|state| state.finish(self.id));
+    }
+}
+
+/// Run an optimistic call of `store`, and restore it with `restore` if the call fails.
+///
+/// The call runs at once until its first `.await`, so that its change is shown right away, and
+/// the rest of it is queued after the previous calls. A failed call restores the store as it was
+/// before it, which also undoes the calls made after it, so these are cancelled before they run
+/// again.
+#[doc(hidden)]
+pub fn optimistic<S, F, Fut>(
+    state: &'static LocalKey<OptimisticCalls>,
+    calls: Calls,
+    store: S,
+    restore: fn(&mut S, S::Snapshot),
+    call: F,
+) where
+    S: Snapshot + Copy + PartialEq,
+    F: FnOnce(CancellationToken) -> Fut,
+    Fut: Future<Output: OptimisticResult> + 'static,
+{
+    let snapshot = store.snapshot();
+    let token = CancellationToken::new();
+    let guard = OptimisticGuard {
+        state,
+        id: state.with(|state| state.start(store, token.clone())),
+    };
+    let settle = move |result: Fut::Output| {
+        if result.is_failure() {
+            let mut store = store;
+            restore(&mut store, snapshot);
+            state.with(|state| state.undo_after(&store, guard.id));
+        }
+        drop(guard);
+    };
+
+    // The call is polled again once it's its turn, so the waker doesn't need to wake anything
+    let mut future = Box::pin(call(token.clone()));
+    if let Poll::Ready(result) = future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        settle(result);
+        return;
+    }
+
+    spawn_concurrently(calls, store, Concurrency::Queue, move |_| {
+        async move {
+            // An earlier call failed, and already undid this one
+            if !token.is_cancelled() {
+                settle(future.await);
+            }
+        }
+    });
+}
+
+/// A call of an action by `store`, that is forgotten once its task ends, even if the task is
+/// dropped with its component.
+struct Finish<S: PartialEq + 'static> {
+    state: &'static LocalKey<ActionState>,
+    store: S,
+    /// `None` once the call is forgotten, so that a newer call with the same number isn't.
+    call: Option<u64>,
+}
+
+impl<S: Copy + PartialEq + 'static> Finish<S> {
+    /// Count a new call of the action by `store`.
+    fn new(state: &'static LocalKey<ActionState>, store: S) -> Self {
+        Self {
+            state,
+            store,
+            call: Some(state.with(|state| state.call(store))),
+        }
+    }
+}
+
+impl<S: PartialEq + 'static> Finish<S> {
+    /// Forget the call, and return if it was the last one of the store.
+    fn finish(&mut self) -> bool {
+        let Some(call) = self.call.take() else {
+            return false;
+        };
+        // The state is already gone if the task is dropped along with the thread
+        self.state
+            .try_with(|state| state.finish(&self.store, call))
+            .unwrap_or(false)
+    }
+}
+
+impl<S: PartialEq + 'static> Drop for Finish<S> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Run `action` once `delay` has passed without another call for the same `store`.
+#[doc(hidden)]
+pub fn debounce<Tm, S, F, Fut>(
+    state: &'static LocalKey<ActionState>,
+    store: S,
+    delay: Duration,
+    action: F,
+) where
+    Tm: Timer,
+    S: Copy + PartialEq + 'static,
+    F: FnOnce() -> Fut + 'static,
+    Fut: Future<Output = ()>,
+{
+    let mut finish = Finish::new(state, store);
+    // The delay starts with the call, even if the task is polled later
+    let sleep = Tm::sleep(delay);
+
+    spawn(async move {
+        sleep.await;
+
+        if finish.finish() {
+            action().await;
+        }
+    });
+}
+
+/// Run `action` now, unless a previous call for the same `store` started less than `delay` ago, or
+/// is still running, in which case this call is dropped.
+#[doc(hidden)]
+pub fn throttle<Tm, S, F, Fut>(
+    state: &'static LocalKey<ActionState>,
+    store: S,
+    delay: Duration,
+    action: F,
+) where
+    Tm: Timer,
+    S: Copy + PartialEq + 'static,
+    F: FnOnce() -> Fut + 'static,
+    Fut: Future<Output = ()>,
+{
+    if state.with(|state| state.is_waiting(&store)) {
+        return;
+    }
+    let finish = Finish::new(state, store);
+    // The delay starts with the call, not once the action is done
+    let start = Tm::now();
+
+    spawn(async move {
+        let _finish = finish;
+        action().await;
+        Tm::sleep(delay.saturating_sub(Tm::now().saturating_sub(start))).await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::testing::{dom, in_app, MockTimer},
+        dioxus::prelude::{Readable, ReadableVecExt, Signal, Writable, WritableVecExt},
+        std::rc::Rc,
+    };
+
+    const DELAY: Duration = Duration::from_millis(100);
+
+    type Log = Rc<RefCell<Vec<u32>>>;
+
+    /// An action of the store `0` that logs `value` once it ran.
+    fn log(log: &Log, value: u32) -> impl FnOnce() -> std::future::Ready<()> + 'static {
+        let log = Rc::clone(log);
+        move || {
+            log.borrow_mut().push(value);
+            std::future::ready(())
+        }
+    }
+
+    fn is_idle(state: &'static LocalKey<ActionState>) -> bool {
+        state.with(|state| state.calls.borrow().is_empty())
+    }
+
+    #[test]
+    fn debounce_runs_the_last_call_once_the_calls_stop() {
+        thread_local! {
+            static STATE: ActionState = const { ActionState::new() };
+        }
+        let mut dom = dom();
+        let calls = Log::default();
+
+        in_app(&dom, || {
+            debounce::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, log(&calls, 1));
+        });
+        MockTimer::advance(DELAY / 2);
+        dom.process_events();
+        in_app(&dom, || {
+            debounce::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, log(&calls, 2));
+        });
+
+        // The first call is over, but the second one started the delay again
+        MockTimer::advance(DELAY * 3 / 4);
+        dom.process_events();
+        assert!(calls.borrow().is_empty());
+
+        MockTimer::advance(DELAY / 2);
+        dom.process_events();
+        assert_eq!(*calls.borrow(), [2]);
+        assert!(is_idle(&STATE));
+    }
+
+    #[test]
+    fn debounce_keeps_a_call_made_while_the_action_runs() {
+        thread_local! {
+            static STATE: ActionState = const { ActionState::new() };
+        }
+        let mut dom = dom();
+        let (calls, gate) = (Log::default(), CancellationToken::new());
+
+        in_app(&dom, || {
+            let (calls, gate) = (Rc::clone(&calls), gate.clone());
+            debounce::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, move || {
+                async move {
+                    gate.cancelled().await;
+                    calls.borrow_mut().push(1);
+                }
+            });
+        });
+        MockTimer::advance(DELAY);
+        dom.process_events();
+
+        // The second call gets the same number as the first one, which is still running
+        in_app(&dom, || {
+            debounce::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, log(&calls, 2));
+        });
+        gate.cancel();
+        dom.process_events();
+        assert_eq!(*calls.borrow(), [1]);
+
+        MockTimer::advance(DELAY);
+        dom.process_events();
+        assert_eq!(*calls.borrow(), [1, 2]);
+    }
+
+    #[test]
+    fn debounce_forgets_a_call_whose_task_is_dropped() {
+        thread_local! {
+            static STATE: ActionState = const { ActionState::new() };
+        }
+        let dom = dom();
+        let calls = Log::default();
+
+        in_app(&dom, || {
+            debounce::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, log(&calls, 1));
+        });
+        assert!(!is_idle(&STATE));
+
+        drop(dom);
+        assert!(is_idle(&STATE));
+        assert!(calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn throttle_drops_the_calls_until_the_delay_passed() {
+        thread_local! {
+            static STATE: ActionState = const { ActionState::new() };
+        }
+        let mut dom = dom();
+        let calls = Log::default();
+
+        in_app(&dom, || {
+            throttle::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, log(&calls, 1));
+            throttle::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, log(&calls, 2));
+            // Another store isn't throttled
+            throttle::<MockTimer, _, _, _>(&STATE, 1_u8, DELAY, log(&calls, 3));
+        });
+        dom.process_events();
+        assert_eq!(*calls.borrow(), [1, 3]);
+
+        MockTimer::advance(DELAY);
+        dom.process_events();
+        in_app(&dom, || {
+            throttle::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, log(&calls, 4));
+        });
+        dom.process_events();
+        assert_eq!(*calls.borrow(), [1, 3, 4]);
+    }
+
+    #[test]
+    fn the_throttle_delay_starts_with_the_call() {
+        thread_local! {
+            static STATE: ActionState = const { ActionState::new() };
+        }
+        let mut dom = dom();
+        let calls = Log::default();
+
+        in_app(&dom, || {
+            let calls = Rc::clone(&calls);
+            throttle::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, move || {
+                async move {
+                    MockTimer::sleep(DELAY * 3 / 4).await;
+                    calls.borrow_mut().push(1);
+                }
+            });
+        });
+        dom.process_events();
+        MockTimer::advance(DELAY * 3 / 4);
+        dom.process_events();
+        assert_eq!(*calls.borrow(), [1]);
+
+        // The action took most of the delay, so the next call can run soon after it
+        MockTimer::advance(DELAY / 4);
+        dom.process_events();
+        assert!(is_idle(&STATE));
+        in_app(&dom, || {
+            throttle::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, log(&calls, 2));
+        });
+        dom.process_events();
+        assert_eq!(*calls.borrow(), [1, 2]);
+    }
+
+    #[test]
+    fn throttle_forgets_a_call_whose_task_is_dropped() {
+        thread_local! {
+            static STATE: ActionState = const { ActionState::new() };
+        }
+        let mut dom = dom();
+        let calls = Log::default();
+
+        in_app(&dom, || {
+            throttle::<MockTimer, _, _, _>(&STATE, 0_u8, DELAY, log(&calls, 1));
+        });
+        dom.process_events();
+        assert!(!is_idle(&STATE));
+
+        drop(dom);
+        assert!(is_idle(&STATE));
+    }
+
+    /// A store with a list of items, that an optimistic call adds to.
+    #[derive(Clone, Copy, PartialEq)]
+    struct Items(Signal<Vec<u32>>);
+
+    impl Snapshot for Items {
+        type Snapshot = Vec<u32>;
+
+        fn snapshot(&self) -> Vec<u32> {
+            self.0.peek().clone()
+        }
+
+        fn restore(&mut self, snapshot: Vec<u32>) {
+            self.0.set(snapshot);
+        }
+    }
+
+    /// Add `value` to `items` at once, then fail once `gate` is cancelled if `fails`.
+    fn add(
+        state: &'static LocalKey<OptimisticCalls>,
+        calls: &Calls,
+        items: Items,
+        value: u32,
+        gate: &CancellationToken,
+        fails: bool,
+    ) -> CancellationToken {
+        let (gate, tokens) = (gate.clone(), Rc::new(RefCell::new(None)));
+        optimistic(state, calls.clone(), items, Items::restore, {
+            let tokens = Rc::clone(&tokens);
+            move |token| {
+                async move {
+                    let mut items = items;
+                    items.0.push(value);
+                    tokens.replace(Some(token));
+                    gate.cancelled().await;
+                    if fails {
+                        Err(())
+                    } else {
+                        Ok(())
+                    }
+                }
+            }
+        });
+        tokens.take().unwrap()
+    }
+
+    #[test]
+    fn an_optimistic_call_changes_the_store_at_once() {
+        thread_local! {
+            static STATE: OptimisticCalls = const { OptimisticCalls::new() };
+        }
+        let mut dom = dom();
+        let (calls, gate) = (Calls::default(), CancellationToken::new());
+        let items = in_app(&dom, || Items(Signal::new(Vec::new())));
+
+        // The second call is queued, but its change is already shown
+        in_app(&dom, || {
+            add(&STATE, &calls, items, 1, &gate, false);
+            add(&STATE, &calls, items, 2, &gate, true);
+        });
+        assert_eq!(in_app(&dom, || items.0.cloned()), [1, 2]);
+
+        gate.cancel();
+        for _ in 0..10 {
+            dom.process_events();
+        }
+        assert_eq!(in_app(&dom, || items.0.cloned()), [1]);
+        assert!(STATE.with(|state| state.calls.borrow().is_empty()));
+    }
+
+    #[test]
+    fn a_failed_optimistic_call_undoes_the_calls_made_after_it() {
+        thread_local! {
+            static STATE: OptimisticCalls = const { OptimisticCalls::new() };
+        }
+        let mut dom = dom();
+        let calls = Calls::default();
+        let (failed, later) = (CancellationToken::new(), CancellationToken::new());
+        let items = in_app(&dom, || Items(Signal::new(Vec::new())));
+
+        let tokens = in_app(&dom, || {
+            add(&STATE, &calls, items, 1, &failed, true);
+            [
+                add(&STATE, &calls, items, 2, &later, false),
+                add(&STATE, &calls, items, 3, &later, true),
+            ]
+        });
+        later.cancel();
+        failed.cancel();
+        for _ in 0..10 {
+            dom.process_events();
+        }
+
+        // The third call would have restored the first change if it ran
+        assert!(in_app(&dom, || items.0.is_empty()));
+        assert!(tokens.iter().all(CancellationToken::is_cancelled));
+        assert!(STATE.with(|state| state.calls.borrow().is_empty()));
+    }
+}
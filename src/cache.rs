@@ -0,0 +1,650 @@
+use {
+    crate::Timer,
+    dioxus::prelude::{spawn, Readable, ScopeId, Signal, Writable},
+    std::{
+        any::{Any, TypeId},
+        cell::{Cell, RefCell},
+        collections::HashMap,
+        future::{poll_fn, Future},
+        hash::Hash,
+        marker::PhantomData,
+        rc::Rc,
+        task::{Poll, Waker},
+        time::Duration,
+    },
+};
+
+thread_local! {
+    /// The cached values, by type of key.
+    static CACHE: RefCell<HashMap<TypeId, Box<dyn Any>>> = RefCell::new(HashMap::new());
+    /// The last version given to a loaded value.
+    static LAST_VERSION: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The cached values of every key of type `K`.
+type Keys<K> = HashMap<K, KeyState>;
+
+/// The cached values of a key, by type of value.
+///
+/// They are dropped once no resource uses the key and no value of the key is being loaded.
+struct KeyState {
+    /// Written when a value of the key is loaded or invalidated, so that the resources that read
+    /// the key are restarted.
+    changes: Signal<u64>,
+    entries: HashMap<TypeId, Box<dyn AnyEntry>>,
+    /// The number of resources whose last query was this key.
+    users: usize,
+}
+
+/// The cached value of a key.
+struct Entry<T> {
+    value: Option<T>,
+    loaded_at: Duration,
+    /// A number that is different for every loaded value.
+    version: u64,
+    invalidated: bool,
+    loading: bool,
+    /// The loads of the same key that wait for this one.
+    waiting: Vec<Waker>,
+}
+
+impl<T> Default for Entry<T> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            loaded_at: Duration::ZERO,
+            version: 0,
+            invalidated: false,
+            loading: false,
+            waiting: Vec::new(),
+        }
+    }
+}
+
+/// An [`Entry`] of any type.
+trait AnyEntry {
+    fn invalidate(&mut self);
+    fn is_loading(&self) -> bool;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyEntry for Entry<T> {
+    fn invalidate(&mut self) {
+        self.invalidated = true;
+    }
+
+    fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// What a query does with the cached value.
+///
+/// Every decision that uses a cached value gives its version.
+enum Decision<T> {
+    /// Use the cached value.
+    Cached(T, u64),
+    /// Use the stale cached value, while another query loads a new one.
+    Stale(T, u64),
+    /// Use the stale cached value, and load a new one in the background.
+    Revalidate(T, u64),
+    /// Wait for another query that loads the value.
+    Wait,
+    Load,
+}
+
+/// A resource that reads the cache, created once for each resource with a `key`.
+///
+/// The resource uses the key of its last query until it's dropped along with its component.
+#[doc(hidden)]
+pub struct CacheUser<K: Hash + Eq + Clone + 'static>(Rc<UserState<K>>);
+
+struct UserState<K: Hash + Eq + Clone + 'static> {
+    key: RefCell<Option<K>>,
+    /// The version of the value last used by the resource, so that a value that was just loaded
+    /// isn't loaded again when the resource is restarted.
+    seen: Cell<Option<u64>>,
+}
+
+impl<K: Hash + Eq + Clone + 'static> CacheUser<K> {
+    pub fn new() -> Self {
+        Self(Rc::new(UserState {
+            key: RefCell::new(None),
+            seen: Cell::new(None),
+        }))
+    }
+
+    /// Use `key`, instead of the key of the previous query if it's another one.
+    fn use_key(&self, key: &K) {
+        let previous = {
+            let mut current = self.0.key.borrow_mut();
+            if current.as_ref() == Some(key) {
+                return;
+            }
+            current.replace(key.clone())
+        };
+
+        self.0.seen.set(None);
+        with_key_state(key, |key_state| key_state.users += 1);
+        if let Some(previous) = previous {
+            release(&previous);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone + 'static> Default for CacheUser<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone + 'static> Clone for CacheUser<K> {
+    fn clone(&self) -> Self {
+        Self(Rc::clone(&self.0))
+    }
+}
+
+impl<K: Hash + Eq + Clone + 'static> Drop for UserState<K> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            release(&key);
+        }
+    }
+}
+
+/// Run `f` with the state of `key`.
+fn with_key_state<K: Hash + Eq + Clone + 'static, O>(
+    key: &K,
+    f: impl FnOnce(&mut KeyState) -> O,
+) -> O {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let keys = cache
+            .entry(TypeId::of::<K>())
+            .or_insert_with(|| Box::new(Keys::<K>::new()))
+            .downcast_mut::<Keys<K>>()
+            .expect("the keys are stored by type");
+        let key_state = keys.entry(key.clone()).or_insert_with(|| {
+            KeyState {
+                changes: Signal::new_in_scope(0, ScopeId::ROOT),
+                entries: HashMap::new(),
+                users: 0,
+            }
+        });
+
+        f(key_state)
+    })
+}
+
+/// Stop using `key`, and drop its values if it was the last resource to use it.
+fn release<K: Hash + Eq + 'static>(key: &K) {
+    with_keys(|keys: &mut Keys<K>| {
+        if let Some(key_state) = keys.get_mut(key) {
+            key_state.users = key_state.users.saturating_sub(1);
+        }
+    });
+    evict(key);
+}
+
+/// Drop the values of `key` and its signal, unless a resource uses it or a value is being loaded.
+fn evict<K: Hash + Eq + 'static>(key: &K) {
+    with_keys(|keys: &mut Keys<K>| {
+        let unused = keys.get(key).is_some_and(|key_state| {
+            key_state.users == 0 && !key_state.entries.values().any(|entry| entry.is_loading())
+        });
+        if unused && let Some(key_state) = keys.remove(key) {
+            key_state.changes.manually_drop();
+        }
+    });
+}
+
+/// Run `f` with the keys of type `K`, if the cache still exists, since a resource can be dropped
+/// along with the thread.
+fn with_keys<K: Hash + Eq + 'static>(f: impl FnOnce(&mut Keys<K>)) {
+    let _ = CACHE.try_with(|cache| {
+        if let Some(keys) = cache
+            .borrow_mut()
+            .get_mut(&TypeId::of::<K>())
+            .and_then(|keys| keys.downcast_mut::<Keys<K>>())
+        {
+            f(keys);
+        }
+    });
+}
+
+/// Run `f` with the state of `key`, and the entry of type `T` of this key.
+fn with_entry<K: Hash + Eq + Clone + 'static, T: 'static, O>(
+    key: &K,
+    f: impl FnOnce(&mut KeyState, &mut Entry<T>) -> O,
+) -> O {
+    with_key_state(key, |key_state| {
+        let mut entry = key_state
+            .entries
+            .remove(&TypeId::of::<T>())
+            .unwrap_or_else(|| Box::new(Entry::<T>::default()));
+        let output = f(
+            key_state,
+            entry
+                .as_any_mut()
+                .downcast_mut::<Entry<T>>()
+                .expect("the entries are stored by type"),
+        );
+        key_state.entries.insert(TypeId::of::<T>(), entry);
+
+        output
+    })
+}
+
+/// Load a value of the cache, and give it to the queries that wait for it.
+///
+/// If this is cancelled, the queries that wait for it load the value themselves.
+async fn load_entry<Tm, K, T, F, Fut>(key: K, load: F) -> (T, u64)
+where
+    Tm: Timer,
+    K: Hash + Eq + Clone + 'static,
+    T: Clone + 'static,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    /// Stop loading the entry if the load is cancelled.
+    struct Loading<K: Hash + Eq + Clone + 'static, T: 'static> {
+        key: Option<K>,
+        value: PhantomData<T>,
+    }
+
+    impl<K: Hash + Eq + Clone + 'static, T: 'static> Drop for Loading<K, T> {
+        fn drop(&mut self) {
+            if let Some(key) = self.key.take() {
+                with_entry::<K, T, _>(&key, |_, entry| {
+                    entry.loading = false;
+                    entry.waiting.drain(..).for_each(Waker::wake);
+                });
+                evict(&key);
+            }
+        }
+    }
+
+    let mut loading = Loading::<K, T> {
+        key: Some(key.clone()),
+        value: PhantomData,
+    };
+    let value = load().await;
+    loading.key = None;
+
+    let version = LAST_VERSION.with(|last_version| {
+        last_version.set(last_version.get() + 1);
+        last_version.get()
+    });
+    let mut changes = with_entry(&key, |key_state, entry| {
+        entry.value = Some(value.clone());
+        entry.loaded_at = Tm::now();
+        entry.version = version;
+        entry.invalidated = false;
+        entry.loading = false;
+        entry.waiting.drain(..).for_each(Waker::wake);
+        key_state.changes
+    });
+    *changes.write() += 1;
+    evict(&key);
+
+    (value, version)
+}
+
+/// Get the value of `key` from the cache, or load it with `load`.
+///
+/// - A value loaded less than `ttl` ago is used as is.
+/// - A stale value is used while a new one is loaded in the background. The resources that read
+///   the key are restarted once it's loaded.
+/// - A value that is already being loaded by another query isn't loaded twice: the query waits for
+///   it, and uses it even if it's already stale, like without a `ttl`.
+///
+/// `user` is the resource that queries the key, which knows the version of the value it last used,
+/// so that a value that was just loaded isn't loaded again when the resource is restarted. The
+/// values of a key are dropped once no resource uses it.
+///
+/// This is used by the resources with a `key`.
+#[doc(hidden)]
+pub async fn query<Tm, K, T, F, Fut>(key: K, ttl: Duration, user: CacheUser<K>, load: F) -> T
+where
+    Tm: Timer,
+    K: Hash + Eq + Clone + 'static,
+    T: Clone + 'static,
+    F: FnOnce() -> Fut + 'static,
+    Fut: Future<Output = T>,
+{
+    user.use_key(&key);
+    let seen = user.0.seen.get();
+    let mut waited = false;
+
+    let decision = loop {
+        let decision = with_entry(&key, |_, entry: &mut Entry<T>| {
+            let Some(value) = &entry.value else {
+                if entry.loading {
+                    return Decision::Wait;
+                }
+                entry.loading = true;
+                return Decision::Load;
+            };
+
+            // A value loaded since the last query of the resource, or by the load this query waited
+            // for, is used even if it's stale, otherwise it would be loaded again at once
+            let fresh = !entry.invalidated && Tm::now().saturating_sub(entry.loaded_at) < ttl;
+            let new =
+                !entry.invalidated && (waited || seen.is_some_and(|seen| seen != entry.version));
+            if fresh || new {
+                Decision::Cached(value.clone(), entry.version)
+            } else if entry.loading {
+                Decision::Stale(value.clone(), entry.version)
+            } else {
+                entry.loading = true;
+                Decision::Revalidate(value.clone(), entry.version)
+            }
+        });
+
+        if !matches!(decision, Decision::Wait) {
+            break decision;
+        }
+
+        let mut waiting = false;
+        poll_fn(|cx| {
+            if waiting {
+                return Poll::Ready(());
+            }
+            waiting = true;
+            with_entry(&key, |_, entry: &mut Entry<T>| {
+                entry.waiting.push(cx.waker().clone());
+            });
+            Poll::Pending
+        })
+        .await;
+        waited = true;
+    };
+
+    let (value, version) = match decision {
+        Decision::Cached(value, version) | Decision::Stale(value, version) => (value, version),
+        Decision::Revalidate(value, version) => {
+            let key = key.clone();
+            spawn(async move {
+                load_entry::<Tm, K, T, F, Fut>(key, load).await;
+            });
+            (value, version)
+        },
+        Decision::Wait | Decision::Load => load_entry::<Tm, K, T, F, Fut>(key.clone(), load).await,
+    };
+    user.0.seen.set(Some(version));
+
+    // The resource is subscribed to the key once the value is loaded, so that it isn't restarted
+    // by its own load
+    let changes = with_entry(&key, |key_state, _: &mut Entry<T>| key_state.changes);
+    changes.read();
+
+    value
+}
+
+/// Mark every cached value of `key` as stale, and reload the resources that use it.
+///
+/// Their stale value is kept until the new one is loaded.
+pub fn invalidate<K: Hash + Eq + 'static>(key: &K) {
+    let changes = CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let key_state = cache
+            .get_mut(&TypeId::of::<K>())?
+            .downcast_mut::<Keys<K>>()?
+            .get_mut(key)?;
+
+        key_state
+            .entries
+            .values_mut()
+            .for_each(|entry| entry.invalidate());
+        Some(key_state.changes)
+    });
+
+    if let Some(mut changes) = changes {
+        *changes.write() += 1;
+    }
+}
+
+/// Get the key of `store` with `key`, which helps the compiler to know the type of the closure.
+#[doc(hidden)]
+pub fn key_of<S, K, F: FnOnce(&S) -> K>(store: &S, key: F) -> K {
+    key(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::testing::{block_on, dom, in_app, poll_once, MockTimer},
+        std::{pin::pin, task::Poll},
+    };
+
+    const TTL: Duration = Duration::from_millis(100);
+
+    thread_local! {
+        static LOADS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    /// Load the number of the load, at once.
+    async fn load() -> u32 {
+        LOADS.set(LOADS.get() + 1);
+        LOADS.get()
+    }
+
+    /// Load the number of the load, after 10ms.
+    async fn slow_load() -> u32 {
+        MockTimer::sleep(Duration::from_millis(10)).await;
+        load().await
+    }
+
+    fn is_cached(key: &&str) -> bool {
+        CACHE.with_borrow(|cache| {
+            cache
+                .get(&TypeId::of::<&str>())
+                .and_then(|keys| keys.downcast_ref::<Keys<&str>>())
+                .is_some_and(|keys| keys.contains_key(key))
+        })
+    }
+
+    #[test]
+    fn queries_of_the_same_key_share_their_load() {
+        let mut dom = dom();
+        in_app(&dom, || {
+            // Without a ttl, the value is already stale when the second query gets it
+            let (first, second) = (CacheUser::new(), CacheUser::new());
+            let mut first = pin!(query::<MockTimer, _, _, _, _>(
+                "cats",
+                Duration::ZERO,
+                first,
+                slow_load
+            ));
+            let mut second = pin!(query::<MockTimer, _, _, _, _>(
+                "cats",
+                Duration::ZERO,
+                second,
+                slow_load
+            ));
+
+            assert_eq!(poll_once(&mut first), Poll::Pending);
+            assert_eq!(poll_once(&mut second), Poll::Pending);
+            assert_eq!(block_on(first), 1);
+            assert_eq!(block_on(second), 1);
+        });
+
+        // A load started in the background would be over by now
+        dom.process_events();
+        MockTimer::advance(Duration::from_millis(10));
+        dom.process_events();
+        assert_eq!(LOADS.get(), 1);
+    }
+
+    #[test]
+    fn a_value_is_reused_until_it_is_stale() {
+        let mut dom = dom();
+        let users = [(); 4].map(|()| CacheUser::new());
+
+        in_app(&dom, || {
+            assert_eq!(
+                block_on(query::<MockTimer, _, _, _, _>(
+                    "cats",
+                    TTL,
+                    users[0].clone(),
+                    load
+                )),
+                1
+            );
+            MockTimer::advance(TTL / 2);
+            assert_eq!(
+                block_on(query::<MockTimer, _, _, _, _>(
+                    "cats",
+                    TTL,
+                    users[1].clone(),
+                    load
+                )),
+                1
+            );
+            assert_eq!(LOADS.get(), 1);
+
+            // A stale value is still given to a new resource, while the new one is loaded
+            MockTimer::advance(TTL);
+            assert_eq!(
+                block_on(query::<MockTimer, _, _, _, _>(
+                    "cats",
+                    TTL,
+                    users[2].clone(),
+                    load
+                )),
+                1
+            );
+        });
+        dom.process_events();
+
+        in_app(&dom, || {
+            assert_eq!(LOADS.get(), 2);
+            assert_eq!(
+                block_on(query::<MockTimer, _, _, _, _>(
+                    "cats",
+                    TTL,
+                    users[3].clone(),
+                    load
+                )),
+                2
+            );
+        });
+    }
+
+    #[test]
+    fn a_resource_restarted_by_a_load_uses_its_value() {
+        let mut dom = dom();
+        let (first, second) = (CacheUser::new(), CacheUser::new());
+
+        in_app(&dom, || {
+            block_on(query::<MockTimer, _, _, _, _>(
+                "cats",
+                Duration::ZERO,
+                first.clone(),
+                load,
+            ));
+            block_on(query::<MockTimer, _, _, _, _>(
+                "cats",
+                Duration::ZERO,
+                second.clone(),
+                load,
+            ));
+        });
+        dom.process_events();
+
+        in_app(&dom, || {
+            assert_eq!(
+                block_on(query::<MockTimer, _, _, _, _>(
+                    "cats",
+                    Duration::ZERO,
+                    first,
+                    load
+                )),
+                2
+            );
+            assert_eq!(
+                block_on(query::<MockTimer, _, _, _, _>(
+                    "cats",
+                    Duration::ZERO,
+                    second,
+                    load
+                )),
+                2
+            );
+            assert_eq!(LOADS.get(), 2);
+        });
+    }
+
+    #[test]
+    fn invalidate_reloads_a_fresh_value() {
+        let mut dom = dom();
+        let user = CacheUser::new();
+
+        in_app(&dom, || {
+            block_on(query::<MockTimer, _, _, _, _>(
+                "cats",
+                TTL,
+                user.clone(),
+                load,
+            ));
+            invalidate(&"cats");
+            assert_eq!(
+                block_on(query::<MockTimer, _, _, _, _>(
+                    "cats",
+                    TTL,
+                    user.clone(),
+                    load
+                )),
+                1
+            );
+        });
+        dom.process_events();
+
+        in_app(&dom, || {
+            assert_eq!(LOADS.get(), 2);
+            assert_eq!(
+                block_on(query::<MockTimer, _, _, _, _>("cats", TTL, user, load)),
+                2
+            );
+        });
+    }
+
+    #[test]
+    fn a_key_is_dropped_with_its_last_resource() {
+        let dom = dom();
+        let (first, second) = (CacheUser::new(), CacheUser::new());
+
+        in_app(&dom, || {
+            block_on(query::<MockTimer, _, _, _, _>(
+                "cats",
+                TTL,
+                first.clone(),
+                load,
+            ));
+            block_on(query::<MockTimer, _, _, _, _>(
+                "cats",
+                TTL,
+                second.clone(),
+                load,
+            ));
+            block_on(query::<MockTimer, _, _, _, _>(
+                "dogs",
+                TTL,
+                second.clone(),
+                load,
+            ));
+        });
+
+        // The second resource now uses another key
+        assert!(is_cached(&"cats"));
+        drop(first);
+        assert!(!is_cached(&"cats"));
+        drop(second);
+        assert!(!is_cached(&"dogs"));
+    }
+}
@@ -0,0 +1,4 @@
+mod store_map;
+mod store_vec;
+
+pub use {store_map::StoreMap, store_vec::StoreVec};
@@ -0,0 +1,299 @@
+use {
+    crate::{FromSnapshot, Nested, NestedOutsideHooks, Snapshot, Store},
+    dioxus::prelude::{use_hook, Readable, ScopeId, Signal, Writable},
+    std::collections::BTreeMap,
+};
+
+/// A map of child stores, sorted by key.
+///
+/// The signals of a child store are dropped when it's removed from the map, or replaced by another
+/// store with the same key.
+///
+/// Like [`StoreVec`](crate::StoreVec), reading the map only subscribes to the map itself, and not
+/// to the child stores.
+///
+/// ## Usage
+/// ```no_run
+/// # use {dioxus::prelude::*, modx::StoreMap};
+/// # #[modx::props(name)]
+/// # #[modx::store]
+/// # struct UserStore {
+/// #     name: String,
+/// # }
+/// # #[component]
+/// # fn User(store: UserStore) -> Element {
+/// #     rsx! {}
+/// # }
+/// #[modx::store]
+/// struct UsersStore {
+///     #[modx(nested)]
+///     users: StoreMap<u64, UserStore>,
+/// }
+///
+/// # fn app() -> Element {
+/// let store = UsersStore::new();
+/// store.users().insert(42, UserStoreProps { name: String::from("Ferris") });
+///
+/// rsx! {
+///     for (id, user) in store.users().iter_keyed() {
+///         User { key: "{id}", store: user }
+///     }
+/// }
+/// # }
+/// ```
+pub struct StoreMap<K: 'static, S: 'static> {
+    entries: Signal<BTreeMap<K, S>>,
+}
+
+impl<K: Ord + Clone + 'static, S: Store> StoreMap<K, S> {
+    /// Create an empty map, owned by the current owner.
+    pub fn new() -> Self {
+        Self {
+            entries: Signal::new(BTreeMap::new()),
+        }
+    }
+
+    /// Create an empty map, owned by the scope `owner`.
+    pub fn new_in(owner: ScopeId) -> Self {
+        Self {
+            entries: Signal::new_in_scope(BTreeMap::new(), owner),
+        }
+    }
+
+    /// Create a child store with the key `key`.
+    ///
+    /// If there was already a store with this key, its signals are dropped.
+    pub fn insert(&mut self, key: K, props: S::Props) -> S {
+        let store = S::new_in(self.entries.origin_scope(), props);
+
+        if let Some(previous) = self.entries.write().insert(key, store) {
+            previous.dispose();
+        }
+
+        store
+    }
+
+    /// Remove the child store with the key `key`, and drop its signals.
+    ///
+    /// Returns `false` if there is no such store.
+    pub fn remove(&mut self, key: &K) -> bool {
+        if !self.entries.peek().contains_key(key) {
+            return false;
+        }
+
+        if let Some(store) = self.entries.write().remove(key) {
+            store.dispose();
+        }
+        true
+    }
+
+    /// Only keep the child stores for which `keep` returns `true`, and drop the signals of the
+    /// others.
+    pub fn retain<F: FnMut(&K, S) -> bool>(&mut self, mut keep: F) {
+        let removed = self
+            .entries
+            .peek()
+            .iter()
+            .filter(|&(key, &store)| !keep(key, store))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        for key in removed {
+            self.remove(&key);
+        }
+    }
+
+    /// Remove every child store, and drop their signals.
+    pub fn clear(&mut self) {
+        let entries = std::mem::take(&mut *self.entries.write());
+
+        for store in entries.into_values() {
+            store.dispose();
+        }
+    }
+
+    /// The child store with the key `key`.
+    pub fn get(&self, key: &K) -> Option<S> {
+        self.entries.read().get(key).copied()
+    }
+
+    /// If there is a child store with the key `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.read().contains_key(key)
+    }
+
+    /// Number of child stores in the map.
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    /// If there is no child store in the map.
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().is_empty()
+    }
+
+    /// Iterate over the keys of the map.
+    pub fn keys(&self) -> impl Iterator<Item = K> {
+        self.iter_keyed().map(|(key, _)| key)
+    }
+
+    /// Iterate over the child stores.
+    pub fn iter(&self) -> impl Iterator<Item = S> {
+        self.iter_keyed().map(|(_, store)| store)
+    }
+
+    /// Iterate over the child stores along with their key.
+    pub fn iter_keyed(&self) -> impl Iterator<Item = (K, S)> {
+        self.entries.read().clone().into_iter()
+    }
+}
+
+impl<K: Ord + Clone + 'static, S: Store> Default for StoreMap<K, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone + 'static, S: Store> Nested for StoreMap<K, S> {
+    fn use_nested() -> Self {
+        use_hook(Self::new)
+    }
+}
+
+impl<K: Ord + Clone + 'static, S: Store> NestedOutsideHooks for StoreMap<K, S> {
+    fn new_nested(owner: Option<ScopeId>) -> Self {
+        match owner {
+            Some(owner) => Self::new_in(owner),
+            None => Self::new(),
+        }
+    }
+
+    fn dispose_nested(mut self) {
+        self.clear();
+        self.entries.manually_drop();
+    }
+}
+
+impl<K: Ord + Clone + 'static, S: Store + FromSnapshot + PartialEq> Snapshot for StoreMap<K, S> {
+    type Snapshot = Vec<(K, S, S::Snapshot)>;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        self.entries
+            .peek()
+            .iter()
+            .map(|(key, &store)| (key.clone(), store, store.snapshot()))
+            .collect()
+    }
+
+    /// Restore the child stores. The stores added since the snapshot are removed, and the ones
+    /// that were removed or replaced are created again, with new signals.
+    fn restore(&mut self, snapshot: Self::Snapshot) {
+        let owner = self.entries.origin_scope();
+        let mut current = self.entries.peek().clone();
+
+        // A store replaced since the snapshot has other signals, which are dropped along with it
+        let entries = snapshot
+            .into_iter()
+            .map(|(key, mut store, store_snapshot)| {
+                let is_current = current
+                    .get(&key)
+                    .is_some_and(|current_store| *current_store == store);
+                if is_current {
+                    current.remove(&key);
+                    store.restore(store_snapshot);
+                    (key, store)
+                } else {
+                    (key, S::from_snapshot(owner, store_snapshot))
+                }
+            })
+            .collect::<BTreeMap<_, _>>();
+        *self.entries.write() = entries;
+
+        for store in current.into_values() {
+            store.dispose();
+        }
+    }
+}
+
+impl<K: Ord + Clone + 'static, S: Store + FromSnapshot + PartialEq> FromSnapshot
+    for StoreMap<K, S>
+{
+    fn from_snapshot(owner: ScopeId, snapshot: Self::Snapshot) -> Self {
+        let mut store_map = Self::new_in(owner);
+        store_map.restore(snapshot);
+        store_map
+    }
+}
+
+impl<K, S> Clone for StoreMap<K, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, S> Copy for StoreMap<K, S> {}
+
+impl<K, S> PartialEq for StoreMap<K, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::testing::{dom, in_app},
+        dioxus::prelude::*,
+    };
+
+    #[crate::store]
+    struct User {
+        #[modx(props)]
+        name: String,
+    }
+
+    fn names(users: StoreMap<u64, User>) -> Vec<(u64, String)> {
+        users
+            .iter_keyed()
+            .map(|(id, user)| (id, user.name()))
+            .collect()
+    }
+
+    #[test]
+    fn restore_brings_back_the_users_of_the_snapshot() {
+        let dom = dom();
+
+        in_app(&dom, || {
+            let mut users = StoreMap::<u64, User>::new();
+            let mut kept = users.insert(1, UserProps { name: "a".into() });
+            let replaced = users.insert(2, UserProps { name: "b".into() });
+            let snapshot = users.snapshot();
+
+            kept.name.set("c".into());
+            users.insert(2, UserProps { name: "d".into() });
+            users.insert(3, UserProps { name: "e".into() });
+            users.restore(snapshot);
+
+            assert_eq!(names(users), [(1, "a".into()), (2, "b".into())]);
+            // The replaced user is created again, and the other one is kept
+            assert!(users.get(&1) == Some(kept));
+            assert!(users.get(&2) != Some(replaced));
+        });
+    }
+
+    #[test]
+    fn a_map_is_created_from_a_snapshot() {
+        let dom = dom();
+
+        in_app(&dom, || {
+            let mut users = StoreMap::<u64, User>::new();
+            users.insert(1, UserProps { name: "a".into() });
+
+            let restored = StoreMap::<u64, User>::from_snapshot(ScopeId::APP, users.snapshot());
+            users.clear();
+
+            assert_eq!(names(restored), [(1, "a".into())]);
+        });
+    }
+}
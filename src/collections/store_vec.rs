@@ -0,0 +1,362 @@
+use {
+    crate::{FromSnapshot, Nested, NestedOutsideHooks, Snapshot, Store},
+    dioxus::prelude::{use_hook, CopyValue, Readable, ScopeId, Signal, Writable},
+};
+
+/// A list of child stores.
+///
+/// Every child store gets a key that stays the same for as long as it's in the list, so it can be
+/// used as the `key` of an element in `rsx`. The signals of a child store are dropped when it's
+/// removed from the list.
+///
+/// Reading the list only subscribes to the list itself, and not to the child stores. Adding or
+/// removing a store rerenders the components that iterate over the list, but since stores are
+/// compared by identity, the components of the other child stores are memoized.
+///
+/// ## Usage
+/// ```no_run
+/// # use {dioxus::prelude::*, modx::StoreVec};
+/// # #[modx::props(name)]
+/// # #[modx::store]
+/// # struct TaskStore {
+/// #     name: String,
+/// # }
+/// # #[component]
+/// # fn Task(store: TaskStore) -> Element {
+/// #     rsx! {}
+/// # }
+/// #[modx::store]
+/// struct TodoStore {
+///     #[modx(nested)]
+///     tasks: StoreVec<TaskStore>,
+/// }
+///
+/// # fn app() -> Element {
+/// let store = TodoStore::new();
+/// store.tasks().push(TaskStoreProps { name: String::from("Buy a cat") });
+///
+/// rsx! {
+///     for (key, task) in store.tasks().iter_keyed() {
+///         Task { key: "{key}", store: task }
+///     }
+/// }
+/// # }
+/// ```
+pub struct StoreVec<S: 'static> {
+    entries: Signal<Vec<(usize, S)>>,
+    next_key: CopyValue<usize>,
+}
+
+impl<S: Store> StoreVec<S> {
+    /// Create an empty list, owned by the current owner.
+    pub fn new() -> Self {
+        Self {
+            entries: Signal::new(Vec::new()),
+            next_key: CopyValue::new(0),
+        }
+    }
+
+    /// Create an empty list, owned by the scope `owner`.
+    pub fn new_in(owner: ScopeId) -> Self {
+        Self {
+            entries: Signal::new_in_scope(Vec::new(), owner),
+            next_key: CopyValue::new_in_scope(0, owner),
+        }
+    }
+
+    /// Create a child store at the end of the list.
+    pub fn push(&mut self, props: S::Props) -> S {
+        let entry = self.new_entry(props);
+        self.entries.write().push(entry);
+        entry.1
+    }
+
+    /// Create a child store at the position `index` of the list.
+    ///
+    /// ## Panics
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, props: S::Props) -> S {
+        let entry = self.new_entry(props);
+        self.entries.write().insert(index, entry);
+        entry.1
+    }
+
+    /// Remove the child store with the key `key`, and drop its signals.
+    ///
+    /// Returns `false` if there is no such store.
+    pub fn remove(&mut self, key: usize) -> bool {
+        let Some(index) = self
+            .entries
+            .peek()
+            .iter()
+            .position(|&(entry_key, _)| entry_key == key)
+        else {
+            return false;
+        };
+
+        let (_, store) = self.entries.write().remove(index);
+        store.dispose();
+        true
+    }
+
+    /// Only keep the child stores for which `keep` returns `true`, and drop the signals of the
+    /// others.
+    pub fn retain<F: FnMut(S) -> bool>(&mut self, mut keep: F) {
+        let removed = self
+            .entries
+            .peek()
+            .iter()
+            .filter(|&&(_, store)| !keep(store))
+            .copied()
+            .collect::<Vec<_>>();
+
+        if removed.is_empty() {
+            return;
+        }
+
+        self.entries
+            .write()
+            .retain(|(key, _)| !removed.iter().any(|(removed_key, _)| removed_key == key));
+
+        for (_, store) in removed {
+            store.dispose();
+        }
+    }
+
+    /// Remove every child store, and drop their signals.
+    pub fn clear(&mut self) {
+        let entries = std::mem::take(&mut *self.entries.write());
+
+        for (_, store) in entries {
+            store.dispose();
+        }
+    }
+
+    /// The child store with the key `key`.
+    pub fn get(&self, key: usize) -> Option<S> {
+        self.entries
+            .read()
+            .iter()
+            .find(|&&(entry_key, _)| entry_key == key)
+            .map(|&(_, store)| store)
+    }
+
+    /// Number of child stores in the list.
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    /// If there is no child store in the list.
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().is_empty()
+    }
+
+    /// Iterate over the child stores.
+    pub fn iter(&self) -> impl Iterator<Item = S> {
+        self.iter_keyed().map(|(_, store)| store)
+    }
+
+    /// Iterate over the child stores along with their key.
+    pub fn iter_keyed(&self) -> impl Iterator<Item = (usize, S)> {
+        self.entries.read().clone().into_iter()
+    }
+
+    fn new_entry(&mut self, props: S::Props) -> (usize, S) {
+        let key = *self.next_key.peek();
+        *self.next_key.write() += 1;
+
+        (key, S::new_in(self.entries.origin_scope(), props))
+    }
+}
+
+impl<S: Store> Default for StoreVec<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Store> Nested for StoreVec<S> {
+    fn use_nested() -> Self {
+        use_hook(Self::new)
+    }
+}
+
+impl<S: Store> NestedOutsideHooks for StoreVec<S> {
+    fn new_nested(owner: Option<ScopeId>) -> Self {
+        match owner {
+            Some(owner) => Self::new_in(owner),
+            None => Self::new(),
+        }
+    }
+
+    fn dispose_nested(mut self) {
+        self.clear();
+        self.entries.manually_drop();
+        self.next_key.manually_drop();
+    }
+}
+
+impl<S: Store + FromSnapshot> Snapshot for StoreVec<S> {
+    type Snapshot = Vec<(usize, S::Snapshot)>;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        self.entries
+            .peek()
+            .iter()
+            .map(|&(key, store)| (key, store.snapshot()))
+            .collect()
+    }
+
+    /// Restore the child stores and their order. The stores added since the snapshot are removed,
+    /// and the ones that were removed are created again, with new signals.
+    fn restore(&mut self, snapshot: Self::Snapshot) {
+        let owner = self.entries.origin_scope();
+        let current = self.entries.peek().clone();
+        // A store created again by a previous restore has the key of the one in the snapshot
+        let current_store = |key| {
+            current
+                .iter()
+                .find(|&&(current_key, _)| current_key == key)
+                .map(|&(_, store)| store)
+        };
+
+        let entries = snapshot
+            .into_iter()
+            .map(|(key, store_snapshot)| {
+                match current_store(key) {
+                    Some(mut store) => {
+                        store.restore(store_snapshot);
+                        (key, store)
+                    },
+                    None => (key, S::from_snapshot(owner, store_snapshot)),
+                }
+            })
+            .collect::<Vec<_>>();
+        let is_restored = |key| entries.iter().any(|&(restored_key, _)| restored_key == key);
+
+        let removed = current
+            .into_iter()
+            .filter(|&(key, _)| !is_restored(key))
+            .collect::<Vec<_>>();
+        *self.entries.write() = entries;
+
+        for (_, store) in removed {
+            store.dispose();
+        }
+    }
+}
+
+impl<S: Store + FromSnapshot> FromSnapshot for StoreVec<S> {
+    fn from_snapshot(owner: ScopeId, snapshot: Self::Snapshot) -> Self {
+        // The restored stores keep their keys, so the new ones need to come after them
+        let next_key = snapshot.iter().map(|&(key, _)| key + 1).max().unwrap_or(0);
+        let mut store_vec = Self::new_in(owner);
+        store_vec.next_key.set(next_key);
+        store_vec.restore(snapshot);
+        store_vec
+    }
+}
+
+impl<S> Clone for StoreVec<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for StoreVec<S> {}
+
+impl<S> PartialEq for StoreVec<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::testing::{dom, in_app},
+        dioxus::prelude::*,
+    };
+
+    #[crate::store]
+    struct Task {
+        #[modx(props)]
+        name: String,
+    }
+
+    fn names(tasks: StoreVec<Task>) -> Vec<String> {
+        tasks.iter().map(|task| task.name()).collect()
+    }
+
+    #[test]
+    fn restore_brings_back_the_tasks_of_the_snapshot() {
+        let dom = dom();
+
+        in_app(&dom, || {
+            let mut tasks = StoreVec::<Task>::new();
+            let first = tasks.push(TaskProps { name: "a".into() });
+            let mut second = tasks.push(TaskProps { name: "b".into() });
+            let snapshot = tasks.snapshot();
+
+            tasks.remove(0);
+            second.name.set("c".into());
+            let added = tasks.push(TaskProps { name: "d".into() });
+            tasks.restore(snapshot);
+
+            assert_eq!(names(tasks), ["a", "b"]);
+            // The removed task is created again, and the other one is kept
+            assert_eq!(
+                tasks.iter_keyed().map(|(key, _)| key).collect::<Vec<_>>(),
+                [0, 1]
+            );
+            assert!(tasks.get(0) != Some(first));
+            assert!(tasks.get(1) == Some(second));
+            assert!(!tasks.iter().any(|task| task == added));
+        });
+    }
+
+    #[test]
+    fn a_task_created_again_can_be_restored_again() {
+        let dom = dom();
+
+        in_app(&dom, || {
+            let mut tasks = StoreVec::<Task>::new();
+            tasks.push(TaskProps { name: "a".into() });
+            let (first, second) = (tasks.snapshot(), tasks.snapshot());
+
+            tasks.clear();
+            tasks.restore(first);
+            let mut task = tasks.get(0).unwrap();
+            task.name.set("b".into());
+            tasks.restore(second);
+
+            assert_eq!(names(tasks), ["a"]);
+            assert!(tasks.get(0) == Some(task));
+        });
+    }
+
+    #[test]
+    fn a_list_from_a_snapshot_gives_new_keys() {
+        let dom = dom();
+
+        in_app(&dom, || {
+            let mut tasks = StoreVec::<Task>::new();
+            tasks.push(TaskProps { name: "a".into() });
+            tasks.push(TaskProps { name: "b".into() });
+            tasks.remove(0);
+
+            let mut restored = StoreVec::<Task>::from_snapshot(ScopeId::APP, tasks.snapshot());
+            restored.push(TaskProps { name: "c".into() });
+
+            assert_eq!(names(restored), ["b", "c"]);
+            assert_eq!(
+                restored
+                    .iter_keyed()
+                    .map(|(key, _)| key)
+                    .collect::<Vec<_>>(),
+                [1, 2]
+            );
+        });
+    }
+}
@@ -0,0 +1,644 @@
+use {
+    dioxus::prelude::{spawn, ReactiveContext, Task},
+    std::{
+        any::Any,
+        cell::{Cell, RefCell},
+        collections::VecDeque,
+        future::{pending, poll_fn, Future},
+        pin::pin,
+        rc::Rc,
+        task::{Poll, Waker},
+    },
+};
+
+/// What happens when an action is called, or a resource is restarted, while a previous call is
+/// still running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Concurrency {
+    /// Cancel the previous calls, so that only the last one finishes.
+    Latest,
+    /// Wait for the previous calls to finish, and run the calls one after the other.
+    Queue,
+    /// Drop the new call. A resource gets the value of the load that is running instead.
+    DropNew,
+    /// Run every call at the same time.
+    Parallel,
+}
+
+/// Tells a call that it was cancelled, because a newer one was made with
+/// [`Concurrency::Latest`].
+///
+/// A cancelled call is stopped at its next `.await`, so the token is mostly useful to the work
+/// that it gave to something else, like a thread, or to stop a loop that doesn't wait.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Rc<TokenState>);
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: Cell<bool>,
+    wakers: RefCell<Vec<Waker>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.get()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.set(true);
+        self.0.wakers.take().into_iter().for_each(Waker::wake);
+    }
+
+    /// Wait until the token is cancelled.
+    pub async fn cancelled(&self) {
+        poll_fn(|cx| {
+            if self.is_cancelled() {
+                Poll::Ready(())
+            } else {
+                self.0.wakers.borrow_mut().push(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await;
+    }
+}
+
+/// The calls of an action or of a resource that are running or waiting, for every store that made
+/// them.
+///
+/// This is only used by the generated code.
+#[doc(hidden)]
+#[derive(Clone, Default)]
+pub struct Calls(Rc<CallsState>);
+
+#[derive(Default)]
+struct CallsState {
+    stores: RefCell<Vec<StoreCalls>>,
+    /// The number of the last call, for any store.
+    last_id: Cell<u64>,
+}
+
+/// The calls made by one store.
+struct StoreCalls {
+    store: Box<dyn Any>,
+    running: Vec<Running>,
+    /// The calls waiting with [`Concurrency::Queue`], in order.
+    queued: VecDeque<u64>,
+    waiting: Vec<Waker>,
+}
+
+struct Running {
+    id: u64,
+    task: Task,
+    token: CancellationToken,
+    /// The [`Output`] of the call.
+    output: Rc<dyn Any>,
+}
+
+/// The value returned by a call, once it is finished.
+struct Output<T> {
+    finished: Cell<bool>,
+    /// `None` if the call was cancelled, or if its value was already taken.
+    value: RefCell<Option<T>>,
+    wakers: RefCell<Vec<Waker>>,
+}
+
+impl<T> Output<T> {
+    async fn wait(&self) -> Option<T> {
+        poll_fn(|cx| {
+            if self.finished.get() {
+                Poll::Ready(self.value.take())
+            } else {
+                self.wakers.borrow_mut().push(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl Calls {
+    /// Run `f` with the calls of `store`.
+    fn with_store<S: Copy + PartialEq + 'static, O>(
+        &self,
+        store: &S,
+        f: impl FnOnce(&mut StoreCalls) -> O,
+    ) -> O {
+        let mut stores = self.0.stores.borrow_mut();
+        let index = stores
+            .iter()
+            .position(|calls| calls.store.downcast_ref::<S>() == Some(store))
+            .unwrap_or_else(|| {
+                stores.push(StoreCalls {
+                    store: Box::new(*store),
+                    running: Vec::new(),
+                    queued: VecDeque::new(),
+                    waiting: Vec::new(),
+                });
+                stores.len() - 1
+            });
+
+        let output = f(&mut stores[index]);
+
+        let calls = &stores[index];
+        if calls.running.is_empty() && calls.queued.is_empty() {
+            stores.swap_remove(index);
+        }
+
+        output
+    }
+
+    fn next_id(&self) -> u64 {
+        self.0.last_id.set(self.0.last_id.get() + 1);
+        self.0.last_id.get()
+    }
+
+    /// Start `call` in a new task, and return its output.
+    ///
+    /// The call is polled in `context`, so that the signals it reads are tracked by it.
+    fn start<S, T, F, Fut>(
+        &self,
+        store: S,
+        context: Option<ReactiveContext>,
+        call: F,
+    ) -> Rc<Output<T>>
+    where
+        S: Copy + PartialEq + 'static,
+        T: 'static,
+        F: FnOnce(CancellationToken) -> Fut + 'static,
+        Fut: Future<Output = T> + 'static,
+    {
+        /// Finish the call, even if it is cancelled, and let the queued calls run.
+        struct Finish<S: Copy + PartialEq + 'static, T> {
+            calls: Calls,
+            store: S,
+            id: u64,
+            output: Rc<Output<T>>,
+        }
+
+        impl<S: Copy + PartialEq + 'static, T> Drop for Finish<S, T> {
+            fn drop(&mut self) {
+                self.output.finished.set(true);
+                self.output.wakers.take().into_iter().for_each(Waker::wake);
+
+                let waiting = self.calls.with_store(&self.store, |calls| {
+                    calls.running.retain(|running| running.id != self.id);
+                    std::mem::take(&mut calls.waiting)
+                });
+                waiting.into_iter().for_each(Waker::wake);
+            }
+        }
+
+        let id = self.next_id();
+        let token = CancellationToken::new();
+        let output = Rc::new(Output {
+            finished: Cell::new(false),
+            value: RefCell::new(None),
+            wakers: RefCell::new(Vec::new()),
+        });
+
+        let finish = Finish {
+            calls: self.clone(),
+            store,
+            id,
+            output: Rc::clone(&output),
+        };
+        let call_token = token.clone();
+        let task = spawn(async move {
+            let finish = finish;
+            let mut call = pin!(call(call_token));
+            let value = poll_fn(|cx| {
+                match context {
+                    Some(context) => context.run_in(|| call.as_mut().poll(cx)),
+                    None => call.as_mut().poll(cx),
+                }
+            })
+            .await;
+            *finish.output.value.borrow_mut() = Some(value);
+        });
+
+        let any_output: Rc<dyn Any> = Rc::<Output<T>>::clone(&output);
+        self.with_store(&store, |calls| {
+            calls.running.push(Running {
+                id,
+                task,
+                token,
+                output: any_output,
+            });
+        });
+
+        output
+    }
+
+    /// Wait until a new queued call of `store` is the next one, and no call is running.
+    async fn wait_turn<S: Copy + PartialEq + 'static>(&self, store: &S) {
+        /// Leave the queue if the call is dropped while it waits.
+        struct Leave<'a, S: Copy + PartialEq + 'static> {
+            calls: &'a Calls,
+            store: &'a S,
+            id: Option<u64>,
+        }
+
+        impl<S: Copy + PartialEq + 'static> Drop for Leave<'_, S> {
+            fn drop(&mut self) {
+                if let Some(id) = self.id {
+                    let waiting = self.calls.with_store(self.store, |calls| {
+                        calls.queued.retain(|queued| *queued != id);
+                        std::mem::take(&mut calls.waiting)
+                    });
+                    waiting.into_iter().for_each(Waker::wake);
+                }
+            }
+        }
+
+        let id = self.next_id();
+        self.with_store(store, |calls| calls.queued.push_back(id));
+        let mut leave = Leave {
+            calls: self,
+            store,
+            id: Some(id),
+        };
+
+        poll_fn(|cx| {
+            self.with_store(store, |calls| {
+                if calls.running.is_empty() && calls.queued.front() == Some(&id) {
+                    calls.queued.pop_front();
+                    Poll::Ready(())
+                } else {
+                    calls.waiting.push(cx.waker().clone());
+                    Poll::Pending
+                }
+            })
+        })
+        .await;
+
+        leave.id = None;
+    }
+}
+
+/// Run `call` for `store` with the `concurrency` policy, and return its value, or `None` if it was
+/// cancelled or dropped.
+#[doc(hidden)]
+pub async fn run_concurrently<S, T, F, Fut>(
+    calls: Calls,
+    store: S,
+    concurrency: Concurrency,
+    call: F,
+) -> Option<T>
+where
+    S: Copy + PartialEq + 'static,
+    T: 'static,
+    F: FnOnce(CancellationToken) -> Fut + 'static,
+    Fut: Future<Output = T> + 'static,
+{
+    run(calls, store, concurrency, None, call).await
+}
+
+/// Load a resource with the `concurrency` policy.
+///
+/// The load runs in its own task, so that it isn't cancelled when the resource restarts, but the
+/// signals it reads are still tracked by the resource. Its token is cancelled by a newer load with
+/// [`Concurrency::Latest`].
+#[doc(hidden)]
+pub async fn load_concurrently<T, F, Fut>(calls: Calls, concurrency: Concurrency, load: F) -> T
+where
+    T: 'static,
+    F: FnOnce(CancellationToken) -> Fut + 'static,
+    Fut: Future<Output = T> + 'static,
+{
+    let context = ReactiveContext::current();
+    match run(calls, (), concurrency, context, load).await {
+        Some(value) => value,
+        // The resource was restarted, and will be loaded again
+        None => pending().await,
+    }
+}
+
+/// Load a resource that takes a token, without a concurrency policy.
+///
+/// The load is dropped when the resource restarts, so its token is cancelled then, unless the load
+/// was already finished.
+#[doc(hidden)]
+pub async fn load_cancellable<T, F, Fut>(load: F) -> T
+where
+    F: FnOnce(CancellationToken) -> Fut,
+    Fut: Future<Output = T>,
+{
+    let token = CancellationToken::new();
+    let mut cancel = CancelOnDrop(Some(token.clone()));
+    let value = load(token).await;
+    cancel.0 = None;
+    value
+}
+
+/// Cancels its token when it's dropped, unless it was taken out first.
+struct CancelOnDrop(Option<CancellationToken>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = self.0.take() {
+            token.cancel();
+        }
+    }
+}
+
+async fn run<S, T, F, Fut>(
+    calls: Calls,
+    store: S,
+    concurrency: Concurrency,
+    context: Option<ReactiveContext>,
+    call: F,
+) -> Option<T>
+where
+    S: Copy + PartialEq + 'static,
+    T: 'static,
+    F: FnOnce(CancellationToken) -> Fut + 'static,
+    Fut: Future<Output = T> + 'static,
+{
+    let output = match concurrency {
+        Concurrency::Latest => {
+            // The tasks are cancelled once the calls are released, since they finish when dropped
+            let running = calls.with_store(&store, |calls| std::mem::take(&mut calls.running));
+            for Running { task, token, .. } in running {
+                token.cancel();
+                task.cancel();
+            }
+            calls.start(store, context, call)
+        },
+        Concurrency::Queue => {
+            calls.wait_turn(&store).await;
+            calls.start(store, context, call)
+        },
+        Concurrency::DropNew => {
+            let running = calls.with_store(&store, |calls| {
+                calls
+                    .running
+                    .last()
+                    .map(|running| Rc::clone(&running.output))
+            });
+            match running {
+                Some(output) => output.downcast::<Output<T>>().ok()?,
+                None => calls.start(store, context, call),
+            }
+        },
+        Concurrency::Parallel => calls.start(store, context, call),
+    };
+
+    output.wait().await
+}
+
+/// Run the call of an action in a new task, with the `concurrency` policy.
+#[doc(hidden)]
+pub fn spawn_concurrently<S, F, Fut>(calls: Calls, store: S, concurrency: Concurrency, call: F)
+where
+    S: Copy + PartialEq + 'static,
+    F: FnOnce(CancellationToken) -> Fut + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    spawn(async move {
+        run_concurrently(calls, store, concurrency, call).await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::testing::{dom, in_app, poll_once},
+        dioxus::dioxus_core::VirtualDom,
+        std::pin::Pin,
+    };
+
+    type Call = Pin<Box<dyn Future<Output = Option<u32>>>>;
+
+    /// A call of the store `0` that logs when it starts and ends, and waits for `gate` in between.
+    fn call(
+        calls: &Calls,
+        concurrency: Concurrency,
+        log: &Rc<RefCell<Vec<String>>>,
+        gate: &CancellationToken,
+        value: u32,
+    ) -> Call {
+        let (log, gate) = (Rc::clone(log), gate.clone());
+        Box::pin(run_concurrently(
+            calls.clone(),
+            0_u8,
+            concurrency,
+            move |_| {
+                async move {
+                    log.borrow_mut().push(format!("start {value}"));
+                    gate.cancelled().await;
+                    log.borrow_mut().push(format!("end {value}"));
+                    value
+                }
+            },
+        ))
+    }
+
+    /// Poll every call in the app, and run the tasks they spawned, until they are all finished.
+    fn finish(dom: &mut VirtualDom, mut calls: Vec<Call>) -> Vec<Option<u32>> {
+        let mut values = vec![None; calls.len()];
+
+        for _ in 0..100 {
+            in_app(dom, || {
+                for (call, value) in calls.iter_mut().zip(&mut values) {
+                    if value.is_none() &&
+                        let Poll::Ready(output) = poll_once(call)
+                    {
+                        *value = Some(output);
+                    }
+                }
+            });
+            if values.iter().all(Option::is_some) {
+                return values.into_iter().flatten().collect();
+            }
+            dom.process_events();
+        }
+
+        panic!("the calls never finished");
+    }
+
+    fn is_idle(calls: &Calls) -> bool {
+        calls.0.stores.borrow().is_empty()
+    }
+
+    #[test]
+    fn latest_cancels_the_previous_calls() {
+        let mut dom = dom();
+        let (calls, log, gate) = (Calls::default(), Rc::default(), CancellationToken::new());
+        let tokens = Rc::new(RefCell::new(Vec::new()));
+
+        let mut first = Box::pin(run_concurrently(
+            calls.clone(),
+            0_u8,
+            Concurrency::Latest,
+            {
+                let tokens = Rc::clone(&tokens);
+                move |token| {
+                    tokens.borrow_mut().push(token);
+                    pending::<u32>()
+                }
+            },
+        ));
+        in_app(&dom, || assert_eq!(poll_once(&mut first), Poll::Pending));
+        dom.process_events();
+
+        let second = call(&calls, Concurrency::Latest, &log, &gate, 2);
+        gate.cancel();
+
+        assert_eq!(finish(&mut dom, vec![first, second]), [None, Some(2)]);
+        assert!(tokens.borrow().iter().all(CancellationToken::is_cancelled));
+        assert!(is_idle(&calls));
+    }
+
+    #[test]
+    fn queue_runs_the_calls_one_after_the_other() {
+        let mut dom = dom();
+        let (calls, log, gate) = (Calls::default(), Rc::default(), CancellationToken::new());
+
+        let queued = (1..=3)
+            .map(|value| call(&calls, Concurrency::Queue, &log, &gate, value))
+            .collect::<Vec<_>>();
+        gate.cancel();
+
+        assert_eq!(finish(&mut dom, queued), [Some(1), Some(2), Some(3)]);
+        assert_eq!(
+            *log.borrow(),
+            ["start 1", "end 1", "start 2", "end 2", "start 3", "end 3"]
+        );
+        assert!(is_idle(&calls));
+    }
+
+    #[test]
+    fn drop_new_gives_the_output_of_the_running_call() {
+        let mut dom = dom();
+        let (calls, log, gate) = (Calls::default(), Rc::default(), CancellationToken::new());
+
+        // Like a resource that is restarted, the first caller stops waiting, but its call goes on
+        let mut first = call(&calls, Concurrency::DropNew, &log, &gate, 1);
+        in_app(&dom, || assert_eq!(poll_once(&mut first), Poll::Pending));
+        drop(first);
+
+        let second = call(&calls, Concurrency::DropNew, &log, &gate, 2);
+        gate.cancel();
+
+        assert_eq!(finish(&mut dom, vec![second]), [Some(1)]);
+        assert_eq!(*log.borrow(), ["start 1", "end 1"]);
+        assert!(is_idle(&calls));
+    }
+
+    #[test]
+    fn a_queued_call_leaves_the_queue_when_dropped() {
+        let mut dom = dom();
+        let (calls, log, gate) = (Calls::default(), Rc::default(), CancellationToken::new());
+
+        let mut queued = (1..=3)
+            .map(|value| call(&calls, Concurrency::Queue, &log, &gate, value))
+            .collect::<Vec<_>>();
+        in_app(&dom, || {
+            for call in &mut queued {
+                assert_eq!(poll_once(call), Poll::Pending);
+            }
+        });
+        drop(queued.remove(1));
+        gate.cancel();
+
+        assert_eq!(finish(&mut dom, queued), [Some(1), Some(3)]);
+        assert_eq!(*log.borrow(), ["start 1", "end 1", "start 3", "end 3"]);
+        assert!(is_idle(&calls));
+    }
+
+    #[test]
+    fn a_running_call_finishes_when_its_task_is_dropped() {
+        let mut dom = dom();
+        let (calls, log, gate) = (Calls::default(), Rc::default(), CancellationToken::new());
+
+        let mut running = call(&calls, Concurrency::Queue, &log, &gate, 1);
+        in_app(&dom, || assert_eq!(poll_once(&mut running), Poll::Pending));
+        dom.process_events();
+        assert_eq!(*log.borrow(), ["start 1"]);
+
+        // The task is dropped with the scope that spawned it
+        drop(dom);
+        assert_eq!(poll_once(&mut running), Poll::Ready(None));
+        assert!(is_idle(&calls));
+    }
+
+    #[test]
+    fn a_restarted_load_cancels_its_token() {
+        let tokens = Rc::new(RefCell::new(Vec::new()));
+        let load = |tokens: &Rc<RefCell<Vec<CancellationToken>>>| {
+            let tokens = Rc::clone(tokens);
+            load_cancellable(move |token| {
+                tokens.borrow_mut().push(token);
+                pending::<u32>()
+            })
+        };
+
+        // The resource drops its load when it restarts
+        let mut restarted = Box::pin(load(&tokens));
+        assert_eq!(poll_once(&mut restarted), Poll::Pending);
+        drop(restarted);
+
+        let mut finished = Box::pin(load_cancellable({
+            let tokens = Rc::clone(&tokens);
+            move |token| {
+                tokens.borrow_mut().push(token);
+                std::future::ready(1)
+            }
+        }));
+        assert_eq!(poll_once(&mut finished), Poll::Ready(1));
+        drop(finished);
+
+        let cancelled = tokens
+            .borrow()
+            .iter()
+            .map(CancellationToken::is_cancelled)
+            .collect::<Vec<_>>();
+        assert_eq!(cancelled, [true, false]);
+    }
+
+    #[test]
+    fn a_newer_load_cancels_the_token_of_the_previous_one() {
+        let mut dom = dom();
+        let calls = Calls::default();
+        let tokens = Rc::new(RefCell::new(Vec::new()));
+
+        let load = |value: u32| {
+            let tokens = Rc::clone(&tokens);
+            Box::pin(load_concurrently(
+                calls.clone(),
+                Concurrency::Latest,
+                move |token| {
+                    tokens.borrow_mut().push(token.clone());
+                    async move {
+                        if value == 1 {
+                            token.cancelled().await;
+                        }
+                        value
+                    }
+                },
+            ))
+        };
+        let (mut first, mut second) = (load(1), load(2));
+
+        in_app(&dom, || assert_eq!(poll_once(&mut first), Poll::Pending));
+        dom.process_events();
+        in_app(&dom, || assert_eq!(poll_once(&mut second), Poll::Pending));
+        dom.process_events();
+        in_app(&dom, || assert_eq!(poll_once(&mut second), Poll::Ready(2)));
+
+        // The first load waits for the resource to load again instead
+        in_app(&dom, || assert_eq!(poll_once(&mut first), Poll::Pending));
+        let cancelled = tokens
+            .borrow()
+            .iter()
+            .map(CancellationToken::is_cancelled)
+            .collect::<Vec<_>>();
+        assert_eq!(cancelled, [true, false]);
+    }
+}
@@ -0,0 +1,27 @@
+use {
+    dioxus::prelude::{current_scope_id, ReactiveContext, ScopeId},
+    std::{
+        future::{poll_fn, Future},
+        panic::Location,
+        pin::pin,
+    },
+};
+
+/// Poll `future` in a reactive context that never updates anything, so that the signals it reads
+/// don't restart the hook that runs it.
+///
+/// A coroutine usually reads the store that it updates, and would otherwise be restarted, and lose
+/// its messages, by its own writes.
+#[doc(hidden)]
+pub fn untracked<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let context = ReactiveContext::new_with_callback(
+        || {},
+        current_scope_id().unwrap_or(ScopeId::ROOT),
+        Location::caller(),
+    );
+
+    async move {
+        let mut future = pin!(future);
+        poll_fn(|cx| context.run_in(|| future.as_mut().poll(cx))).await
+    }
+}
@@ -0,0 +1,296 @@
+use {
+    dioxus::prelude::{CopyValue, ReactiveContext, Readable, Resource, Signal, Writable},
+    std::{
+        future::{poll_fn, Future},
+        panic::Location,
+        pin::pin,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    },
+};
+
+/// A page of a paginated field, returned by its method.
+///
+/// `next` is the cursor that is given to the method to load the next page, or `None` if this is
+/// the last page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page<T, C> {
+    pub items: Vec<T>,
+    pub next: Option<C>,
+}
+
+/// The result of the method of a paginated field.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "a paginated field must be a `Result<modx::Page<T, C>, E>` whose items and cursor \
+               are `Clone`"
+)]
+pub trait PageResult: 'static {
+    type Item: Clone + 'static;
+    type Cursor: Clone + 'static;
+
+    /// The page, if it was loaded.
+    fn page(&self) -> Option<&Page<Self::Item, Self::Cursor>>;
+}
+
+impl<T: Clone + 'static, C: Clone + 'static, E: 'static> PageResult for Result<Page<T, C>, E> {
+    type Cursor = C;
+    type Item = T;
+
+    fn page(&self) -> Option<&Page<T, C>> {
+        self.as_ref().ok()
+    }
+}
+
+/// The items of every page loaded by a paginated field.
+///
+/// The resource of the field only loads one page at a time: the first one when it's restarted by
+/// the signals it reads, and the next one when `load_more` restarts it.
+#[doc(hidden)]
+pub struct Pages<R: PageResult> {
+    items: Signal<Vec<R::Item>>,
+    next: Signal<Option<R::Cursor>>,
+    loading_more: Signal<bool>,
+    /// Written by `load_more`, to restart the resource.
+    more: Signal<u64>,
+    /// If the next load of the resource is the next page, instead of the first one.
+    more_requested: CopyValue<bool>,
+    /// The cursor of the page being loaded.
+    cursor: CopyValue<Option<R::Cursor>>,
+    /// The signals read by the method, once the resource was loaded.
+    dependencies: CopyValue<Option<Dependencies>>,
+}
+
+/// The signals read by the method of a paginated field, which are tracked apart from `load_more`,
+/// so that a restart is known to come from them.
+#[derive(Clone)]
+struct Dependencies {
+    /// Restarts the resource when a signal read by the method changes.
+    context: ReactiveContext,
+    changed: Arc<AtomicBool>,
+}
+
+impl Dependencies {
+    fn new(resource: ReactiveContext) -> Self {
+        let changed = Arc::new(AtomicBool::new(false));
+        let context = ReactiveContext::new_with_callback(
+            {
+                let changed = Arc::clone(&changed);
+                move || {
+                    changed.store(true, Ordering::Relaxed);
+                    resource.mark_dirty();
+                }
+            },
+            resource.origin_scope(),
+            Location::caller(),
+        );
+        Self { context, changed }
+    }
+
+    /// Run `load` in the context of the dependencies, so that the signals it reads are tracked by
+    /// them instead of the resource.
+    async fn track<T>(&self, load: impl Future<Output = T>) -> T {
+        self.context.clear_subscribers();
+        let mut load = pin!(load);
+        poll_fn(|cx| self.context.run_in(|| load.as_mut().poll(cx))).await
+    }
+}
+
+impl<R: PageResult> Pages<R> {
+    /// Create the pages, owned by the current owner.
+    pub fn new() -> Self {
+        Self {
+            items: Signal::new(Vec::new()),
+            next: Signal::new(None),
+            loading_more: Signal::new(false),
+            more: Signal::new(0),
+            more_requested: CopyValue::new(false),
+            cursor: CopyValue::new(None),
+            dependencies: CopyValue::new(None),
+        }
+    }
+
+    pub fn items(&self) -> Vec<R::Item> {
+        self.items.read().clone()
+    }
+
+    /// The cursor to give to the method of the field, `None` for the first page.
+    pub fn cursor(&self) -> Option<R::Cursor> {
+        self.cursor.peek().clone()
+    }
+
+    pub fn has_more(&self) -> bool {
+        self.next.read().is_some()
+    }
+
+    pub fn is_loading_more(&self) -> bool {
+        *self.loading_more.read()
+    }
+
+    /// Load the next page with `resource`, unless a page is being loaded or there is no next page.
+    pub fn load_more(&mut self, resource: Resource<R>) {
+        if !resource.finished() || self.next.peek().is_none() {
+            return;
+        }
+
+        self.more_requested.set(true);
+        *self.more.write() += 1;
+    }
+
+    /// Forget the loaded pages, and load the first one again with `resource`.
+    pub fn reset(&mut self, mut resource: Resource<R>) {
+        self.items.write().clear();
+        self.next.set(None);
+        self.more_requested.set(false);
+        resource.restart();
+    }
+
+    /// Load a page with `load`, that reads its cursor with `cursor`, and add its items.
+    ///
+    /// The first page replaces the previous items once it's loaded, so that they stay visible
+    /// while the field is loaded again.
+    pub async fn load<F, Fut>(mut self, load: F) -> R
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = R>,
+    {
+        // Subscribe the resource to `load_more`
+        self.more.read();
+
+        let dependencies = ReactiveContext::current().map(|resource| {
+            self.dependencies
+                .write()
+                .get_or_insert_with(|| Dependencies::new(resource))
+                .clone()
+        });
+        // A change of the signals read by the method loads the first page again, even if
+        // `load_more` was called along with it, since the next page is of the previous query
+        let changed = dependencies
+            .as_ref()
+            .is_some_and(|dependencies| dependencies.changed.swap(false, Ordering::Relaxed));
+        let more = *self.more_requested.peek() && !changed;
+        self.more_requested.set(false);
+        let cursor = if more { self.next.peek().clone() } else { None };
+        self.cursor.set(cursor);
+
+        // A restarted load is cancelled, so this is reset by the next one
+        self.loading_more.set(more);
+        let result = match dependencies {
+            Some(dependencies) => dependencies.track(load()).await,
+            None => load().await,
+        };
+
+        if let Some(page) = result.page() {
+            if more {
+                self.items.write().extend(page.items.iter().cloned());
+            } else {
+                self.items.set(page.items.clone());
+            }
+            self.next.set(page.next.clone());
+        }
+        self.loading_more.set(false);
+
+        result
+    }
+}
+
+impl<R: PageResult> Default for Pages<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: PageResult> Clone for Pages<R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R: PageResult> Copy for Pages<R> {}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::testing::{dom_with, in_app},
+        dioxus::{dioxus_core::VirtualDom, prelude::*},
+    };
+
+    /// Three pages of one item for every query, the item being the query and the page.
+    #[crate::store]
+    struct Search {
+        query: u32,
+        #[modx(paginated)]
+        results: Result<Page<u32, u32>, ()>,
+    }
+
+    impl Search {
+        #[expect(
+            clippy::unused_async,
+            reason = "The method of a paginated field is async"
+        )]
+        async fn results(&self, cursor: Option<u32>) -> Result<Page<u32, u32>, ()> {
+            let page = cursor.unwrap_or(0);
+            Ok(Page {
+                items: vec![self.query() * 10 + page],
+                next: (page < 2).then_some(page + 1),
+            })
+        }
+    }
+
+    /// Run the resource until it's loaded, and return its items.
+    fn items(dom: &mut VirtualDom, search: Search) -> Vec<u32> {
+        for _ in 0..10 {
+            dom.process_events();
+        }
+        in_app(dom, || search.results_items())
+    }
+
+    #[test]
+    fn load_more_adds_the_next_page() {
+        let (mut dom, mut search) = dom_with(Search::new);
+        assert_eq!(items(&mut dom, search), [0]);
+
+        in_app(&dom, || search.load_more_results());
+        assert_eq!(items(&mut dom, search), [0, 1]);
+        in_app(&dom, || search.load_more_results());
+        assert_eq!(items(&mut dom, search), [0, 1, 2]);
+
+        // There is no page after the last one
+        assert!(!in_app(&dom, || search.results_has_more()));
+        in_app(&dom, || search.load_more_results());
+        assert_eq!(items(&mut dom, search), [0, 1, 2]);
+    }
+
+    #[test]
+    fn reset_forgets_the_pages_at_once() {
+        let (mut dom, mut search) = dom_with(Search::new);
+        items(&mut dom, search);
+        in_app(&dom, || search.load_more_results());
+        items(&mut dom, search);
+
+        in_app(&dom, || search.reset_results());
+        assert!(in_app(&dom, || search.results_items()).is_empty());
+        assert_eq!(items(&mut dom, search), [0]);
+    }
+
+    #[test]
+    fn a_dependency_change_loads_the_first_page_again() {
+        let (mut dom, mut search) = dom_with(Search::new);
+        items(&mut dom, search);
+        in_app(&dom, || search.load_more_results());
+        assert_eq!(items(&mut dom, search), [0, 1]);
+
+        in_app(&dom, || search.query.set(1));
+        assert_eq!(items(&mut dom, search), [10]);
+
+        // The next page would be of the previous query
+        in_app(&dom, || {
+            search.query.set(2);
+            search.load_more_results();
+        });
+        assert_eq!(items(&mut dom, search), [20]);
+    }
+}
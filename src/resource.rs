@@ -0,0 +1,106 @@
+use {
+    crate::Timer,
+    dioxus::{
+        prelude::{CopyValue, Readable, RenderError, Resource, Writable},
+        CapturedError,
+    },
+    std::{fmt::Display, future::Future, time::Duration},
+};
+
+/// The future returned by the method of a resource field, whose output is the type of the field.
+///
+/// This is only used by the `store` macro to give a clear error when the method doesn't have the
+/// signature `async fn field(&mut self) -> T`.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "the method of a resource field must be `async` and return `{T}`",
+    label = "this returns `{Self}`",
+    note = "a field `x: T` marked as a resource needs a method `async fn x(&mut self) -> T`"
+)]
+pub trait ResourceFuture<T>: Future<Output = T> {}
+
+impl<T, F: Future<Output = T>> ResourceFuture<T> for F {}
+
+/// Check that `future` is the future of a resource of type `T`.
+#[doc(hidden)]
+pub fn resource_future<T, F: ResourceFuture<T>>(future: F) -> impl Future<Output = T> {
+    future
+}
+
+/// The refresh of a resource with `refresh_every`, kept in a hidden field of the store.
+///
+/// The helpers of the store that read the resource mark it as read. Once it has been read this
+/// way, the refresh only loads it again if it was read since its previous load, which it is while
+/// a component displays it, since each load rerenders the components that read it.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+pub struct Refresh {
+    paused: CopyValue<bool>,
+    /// If the resource was ever read through the store, so that its reads are known.
+    tracked: CopyValue<bool>,
+    /// If the resource was read through the store since the refresh last loaded it.
+    read: CopyValue<bool>,
+}
+
+impl Refresh {
+    /// Create the refresh, owned by the current owner.
+    pub fn new() -> Self {
+        Self {
+            paused: CopyValue::new(false),
+            tracked: CopyValue::new(false),
+            read: CopyValue::new(false),
+        }
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused.set(paused);
+    }
+
+    pub fn mark_read(mut self) {
+        self.tracked.set(true);
+        self.read.set(true);
+    }
+
+    /// If the resource should be loaded again, which forgets that it was read.
+    fn take_reload(&mut self) -> bool {
+        if *self.paused.peek() || (*self.tracked.peek() && !*self.read.peek()) {
+            return false;
+        }
+
+        self.read.set(false);
+        true
+    }
+}
+
+impl Default for Refresh {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Restart `resource` every `every`, with the timer `Tm`, unless its refresh is paused, it isn't
+/// read anymore, or the previous load isn't finished yet.
+///
+/// This is spawned by the `store` macro for the resources with `refresh_every`, so it stops when
+/// the scope of the store is dropped.
+pub async fn refresh<Tm: Timer, T>(
+    every: Duration,
+    mut resource: Resource<T>,
+    mut refresh: Refresh,
+) {
+    loop {
+        Tm::sleep(every).await;
+
+        if resource.finished() && refresh.take_reload() {
+            resource.restart();
+        }
+    }
+}
+
+/// Give the error of a resource to the nearest error boundary, as its message.
+///
+/// This is used by the `x_throw` helper of the resources that are a `Result`.
+#[doc(hidden)]
+pub fn render_error<E: Display + ?Sized>(error: &E) -> RenderError {
+    RenderError::Aborted(CapturedError::from_display(error.to_string()))
+}
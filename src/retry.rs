@@ -0,0 +1,250 @@
+use {
+    crate::Timer,
+    std::{
+        fmt,
+        future::{poll_fn, Future},
+        pin::pin,
+        task::Poll,
+        time::Duration,
+    },
+};
+
+/// How the delay between two attempts grows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backoff {
+    /// Always wait `delay`.
+    Fixed,
+    /// Wait `delay`, then `2 * delay`, `3 * delay`...
+    Linear,
+    /// Wait `delay`, then `2 * delay`, `4 * delay`...
+    #[default]
+    Exponential,
+}
+
+/// How a failing load is retried, given with
+/// `#[modx(resource, retry = 3, backoff = "exponential", retry_delay_ms = 100)]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times a load is retried after the first attempt.
+    pub retries: u32,
+    pub backoff: Backoff,
+    /// The delay before the first retry.
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            backoff: Backoff::default(),
+            delay: Duration::from_millis(100),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before the retry number `retry`, starting at 1.
+    pub const fn delay_before(&self, retry: u32) -> Duration {
+        match self.backoff {
+            Backoff::Fixed => self.delay,
+            Backoff::Linear => self.delay.saturating_mul(retry),
+            Backoff::Exponential => {
+                self.delay
+                    .saturating_mul(2_u32.saturating_pow(retry.saturating_sub(1)))
+            },
+        }
+    }
+}
+
+/// The value of a load that can fail, like a `Result`.
+#[diagnostic::on_unimplemented(message = "a resource that is retried must be a `Result`")]
+pub trait Fallible {
+    /// If the load failed, and should be retried.
+    fn is_failure(&self) -> bool;
+}
+
+impl<T, E> Fallible for Result<T, E> {
+    fn is_failure(&self) -> bool {
+        self.is_err()
+    }
+}
+
+/// The value of a load that can time out.
+///
+/// It's implemented for every `Result` whose error can be created from [`TimedOut`].
+#[diagnostic::on_unimplemented(
+    message = "a resource with a timeout must be a `Result` whose error implements \
+               `From<modx::TimedOut>`"
+)]
+pub trait FromTimeout {
+    /// The value of a load that timed out.
+    fn timed_out() -> Self;
+}
+
+impl<T, E: From<TimedOut>> FromTimeout for Result<T, E> {
+    fn timed_out() -> Self {
+        Err(E::from(TimedOut))
+    }
+}
+
+/// The error of a load that took longer than its timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut;
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the load timed out")
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// Call `load` until it succeeds or `policy.retries` retries are done, waiting with the timer
+/// `Tm` between two attempts.
+pub async fn retry<Tm: Timer, T: Fallible, F: Future<Output = T>, L: FnMut() -> F>(
+    policy: RetryPolicy,
+    mut load: L,
+) -> T {
+    let mut value = load().await;
+
+    for retry in 1..=policy.retries {
+        if !value.is_failure() {
+            break;
+        }
+
+        Tm::sleep(policy.delay_before(retry)).await;
+        value = load().await;
+    }
+
+    value
+}
+
+/// Wait for `future`, or return [`FromTimeout::timed_out`] if it takes longer than `duration`
+/// with the timer `Tm`.
+pub async fn timeout<Tm: Timer, T: FromTimeout, F: Future<Output = T>>(
+    duration: Duration,
+    future: F,
+) -> T {
+    let mut future = pin!(future);
+    let mut sleep = pin!(Tm::sleep(duration));
+
+    poll_fn(|cx| {
+        if let Poll::Ready(value) = future.as_mut().poll(cx) {
+            Poll::Ready(value)
+        } else if sleep.as_mut().poll(cx).is_ready() {
+            Poll::Ready(T::timed_out())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::testing::{block_on, MockTimer},
+        std::cell::Cell,
+    };
+
+    const fn policy(retries: u32, backoff: Backoff) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            backoff,
+            delay: Duration::from_millis(100),
+        }
+    }
+
+    #[test]
+    fn delay_before_grows_with_the_backoff() {
+        let delays = |backoff| (1..=4).map(move |retry| policy(4, backoff).delay_before(retry));
+
+        assert!(delays(Backoff::Fixed).eq([100, 100, 100, 100].map(Duration::from_millis)));
+        assert!(delays(Backoff::Linear).eq([100, 200, 300, 400].map(Duration::from_millis)));
+        assert!(delays(Backoff::Exponential).eq([100, 200, 400, 800].map(Duration::from_millis)));
+    }
+
+    #[test]
+    fn retry_waits_between_attempts_until_success() {
+        let attempts = Cell::new(0);
+        let value = block_on(retry::<MockTimer, _, _, _>(
+            policy(5, Backoff::Exponential),
+            || {
+                attempts.set(attempts.get() + 1);
+                let attempt = attempts.get();
+                async move {
+                    if attempt < 3 {
+                        Err(attempt)
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            },
+        ));
+
+        assert_eq!(value, Ok(3));
+        assert_eq!(
+            MockTimer::slept(),
+            [
+                Duration::from_millis(100),
+                Duration::from_millis(200)
+            ]
+        );
+    }
+
+    #[test]
+    fn retry_gives_up_after_the_last_retry() {
+        let attempts = Cell::new(0);
+        let value = block_on(retry::<MockTimer, Result<(), _>, _, _>(
+            policy(2, Backoff::Fixed),
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Err("failed") }
+            },
+        ));
+
+        assert_eq!(value, Err("failed"));
+        assert_eq!(attempts.get(), 3);
+        assert_eq!(MockTimer::now(), Duration::from_millis(200));
+    }
+
+    #[test]
+    fn retry_doesnt_wait_after_a_success() {
+        let value = block_on(retry::<MockTimer, Result<_, ()>, _, _>(
+            policy(3, Backoff::Fixed),
+            || async { Ok(1) },
+        ));
+
+        assert_eq!(value, Ok(1));
+        assert!(MockTimer::slept().is_empty());
+    }
+
+    #[test]
+    fn timeout_lets_a_fast_load_finish() {
+        let value = block_on(timeout::<MockTimer, _, _>(
+            Duration::from_millis(100),
+            async {
+                MockTimer::sleep(Duration::from_millis(50)).await;
+                Ok::<_, TimedOut>(1)
+            },
+        ));
+
+        assert_eq!(value, Ok(1));
+        assert_eq!(MockTimer::now(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn timeout_fails_a_slow_load() {
+        let value = block_on(timeout::<MockTimer, _, _>(
+            Duration::from_millis(100),
+            async {
+                MockTimer::sleep(Duration::from_millis(200)).await;
+                Ok::<_, TimedOut>(1)
+            },
+        ));
+
+        assert_eq!(value, Err(TimedOut));
+        assert_eq!(MockTimer::now(), Duration::from_millis(100));
+    }
+}
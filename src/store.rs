@@ -0,0 +1,224 @@
+use dioxus::prelude::ScopeId;
+
+/// A store declared with [`macro@crate::store`].
+///
+/// This trait is implemented by the `store` macro for every store that can be created outside of
+/// hooks, which means every store without resources.
+pub trait Store: Copy + 'static {
+    /// The props given to the store when it's created. `()` if the store doesn't take any.
+    type Props;
+
+    /// Create the store without calling any hook. Its signals are owned by the scope `owner`.
+    fn new_in(owner: ScopeId, props: Self::Props) -> Self;
+
+    /// Drop every signal of the store.
+    ///
+    /// The store, and every copy of it, must not be read or written afterwards.
+    fn dispose(self);
+}
+
+/// A value that can be a field of a store with `#[modx(nested)]`.
+///
+/// Nested values are handles to their own signals, so they are kept as is in the parent store,
+/// instead of being wrapped in another signal. This trait is implemented by the `store` macro for
+/// every store without props, and by [`StoreVec`](crate::StoreVec) and
+/// [`StoreMap`](crate::StoreMap).
+pub trait Nested: Copy + 'static {
+    /// Create the value when the parent store is created with `new`. This is a hook.
+    fn use_nested() -> Self;
+}
+
+/// A nested value that can also be created outside of hooks, so that the parent store can be
+/// created with `new_in` or `new_detached`, and disposed.
+///
+/// This trait is implemented by the `store` macro for every store without props that can be
+/// created outside of hooks, which means every store without resources, streams or coroutines.
+pub trait NestedOutsideHooks: Nested {
+    /// Create the value without calling any hook, when the parent store is created with `new_in`
+    /// or `new_detached`. The value is owned by `owner`, or by the current owner if it's `None`.
+    fn new_nested(owner: Option<ScopeId>) -> Self;
+
+    /// Drop every signal of the value, when the parent store is disposed.
+    fn dispose_nested(self);
+}
+
+/// A copy of the values of a store, that can be restored later, like when an optimistic action
+/// fails.
+///
+/// This trait is implemented by the `store` macro for every store, as long as the values of its
+/// fields are `Clone`. Nested stores are part of the snapshot, but the values of resources aren't.
+/// It's also implemented by [`StoreVec`](crate::StoreVec) and [`StoreMap`](crate::StoreMap).
+pub trait Snapshot: 'static {
+    type Snapshot: 'static;
+
+    /// Copy the values of the store, without subscribing to them.
+    fn snapshot(&self) -> Self::Snapshot;
+
+    /// Write back the values of `snapshot`. The `readonly` fields of a store are left alone, since
+    /// they can only be written from its module.
+    fn restore(&mut self, snapshot: Self::Snapshot);
+}
+
+/// A store that can be created again from a snapshot, after its signals were dropped.
+///
+/// This is how a store that was removed from a [`StoreVec`](crate::StoreVec) or a
+/// [`StoreMap`](crate::StoreMap) is restored. It's implemented by the `store` macro for every store
+/// that implements [`Store`] and [`Snapshot`].
+pub trait FromSnapshot: Snapshot {
+    /// Create the store with the values of `snapshot`. Its signals are owned by the scope `owner`.
+    fn from_snapshot(owner: ScopeId, snapshot: Self::Snapshot) -> Self;
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            testing::{dom, in_app},
+            StoreVec,
+        },
+        dioxus::prelude::*,
+        std::{
+            panic::Location,
+            sync::{
+                atomic::{AtomicBool, Ordering},
+                Arc,
+            },
+        },
+    };
+
+    #[crate::store]
+    struct Item {
+        #[modx(props)]
+        name: String,
+    }
+
+    #[crate::store]
+    struct Cart {
+        count: u32,
+        #[modx(readonly)]
+        pub(crate) total: u32,
+        #[modx(nested)]
+        items: StoreVec<Item>,
+    }
+
+    #[test]
+    fn restore_writes_back_the_fields_and_the_nested_stores() {
+        let dom = dom();
+
+        in_app(&dom, || {
+            let mut cart = Cart::new_in(ScopeId::APP);
+            cart.items.push(ItemProps { name: "a".into() });
+            let (snapshot, private_snapshot) = (cart.snapshot(), cart.snapshot());
+
+            cart.count.set(1);
+            cart.total.set(10);
+            cart.items.clear();
+            Snapshot::restore(&mut cart, snapshot);
+
+            assert_eq!(cart.count(), 0);
+            assert_eq!(
+                cart.items
+                    .iter()
+                    .map(|item| item.name())
+                    .collect::<Vec<_>>(),
+                ["a"]
+            );
+            // A readonly field can only be restored from the module of the store
+            assert_eq!(cart.total(), 10);
+            cart._modx_restore(private_snapshot);
+            assert_eq!(cart.total(), 0);
+        });
+    }
+
+    #[test]
+    fn a_store_is_created_from_a_snapshot() {
+        let dom = dom();
+
+        in_app(&dom, || {
+            let mut cart = Cart::new_in(ScopeId::APP);
+            cart.count.set(2);
+            cart.items.push(ItemProps { name: "a".into() });
+
+            let restored = Cart::from_snapshot(ScopeId::APP, cart.snapshot());
+            cart.dispose();
+
+            assert_eq!(restored.count(), 2);
+            assert_eq!(restored.items.len(), 1);
+        });
+    }
+
+    #[crate::store]
+    struct Form {
+        #[modx(props)]
+        name: String,
+        age: u32,
+    }
+
+    fn form() -> Form {
+        Form::new_in(ScopeId::APP, FormProps { name: "a".into() })
+    }
+
+    #[test]
+    fn the_changed_fields_are_dirty() {
+        let dom = dom();
+
+        in_app(&dom, || {
+            let mut form = form();
+            assert!(!form.is_dirty());
+
+            form.name.set("b".into());
+            assert!(form.name_is_dirty() && !form.age_is_dirty());
+            assert_eq!(form.dirty_fields(), ["name"]);
+
+            // A field that gets its initial value back isn't dirty anymore
+            form.age.set(1);
+            form.name.set("a".into());
+            assert_eq!(form.dirty_fields(), ["age"]);
+        });
+    }
+
+    #[test]
+    fn reset_gives_back_the_initial_values() {
+        let dom = dom();
+
+        in_app(&dom, || {
+            let mut form = form();
+            form.name.set("b".into());
+            form.age.set(1);
+
+            form.reset_age();
+            assert_eq!(form.dirty_fields(), ["name"]);
+            form.reset();
+            assert_eq!((form.name(), form.age()), ("a".into(), 0));
+            assert!(!form.is_dirty());
+        });
+    }
+
+    #[test]
+    fn mark_clean_rerenders_the_readers_of_is_dirty() {
+        let dom = dom();
+
+        in_app(&dom, || {
+            let mut form = form();
+            form.age.set(1);
+
+            let rerendered = Arc::new(AtomicBool::new(false));
+            let reader = ReactiveContext::new_with_callback(
+                {
+                    let rerendered = Arc::clone(&rerendered);
+                    move || rerendered.store(true, Ordering::Relaxed)
+                },
+                ScopeId::APP,
+                Location::caller(),
+            );
+            assert!(reader.run_in(|| form.is_dirty()));
+
+            form.mark_clean();
+            assert!(rerendered.load(Ordering::Relaxed));
+            assert!(!reader.run_in(|| form.is_dirty()));
+            form.reset();
+            assert_eq!(form.age(), 1);
+        });
+    }
+}
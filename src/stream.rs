@@ -0,0 +1,108 @@
+use {
+    dioxus::{
+        prelude::{Signal, Writable},
+        CapturedError,
+    },
+    futures_core::Stream,
+    std::{
+        error::Error,
+        future::{poll_fn, Future},
+        pin::pin,
+    },
+};
+
+/// The state of a stream field.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum StreamState {
+    /// The stream is created, or gives items.
+    #[default]
+    Running,
+    /// The stream has no more items.
+    Done,
+    /// The stream gave an error, and was stopped.
+    Failed(CapturedError),
+}
+
+/// The type of a stream field, that receives the items of the stream.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "a stream field must be a `Vec` or an `Option` of the items of its stream"
+)]
+pub trait StreamTarget<T> {
+    /// Forget the items of the previous stream.
+    fn reset(&mut self);
+    fn receive(&mut self, item: T);
+}
+
+/// Every item is added at the end.
+impl<T> StreamTarget<T> for Vec<T> {
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn receive(&mut self, item: T) {
+        self.push(item);
+    }
+}
+
+/// Only the last item is kept.
+impl<T> StreamTarget<T> for Option<T> {
+    fn reset(&mut self) {
+        *self = None;
+    }
+
+    fn receive(&mut self, item: T) {
+        *self = Some(item);
+    }
+}
+
+/// An item of a stream, which may be an error that stops it.
+#[doc(hidden)]
+pub trait StreamItem<T> {
+    fn into_result(self) -> Result<T, CapturedError>;
+}
+
+impl<T> StreamItem<T> for T {
+    fn into_result(self) -> Result<T, CapturedError> {
+        Ok(self)
+    }
+}
+
+impl<T, E: Error + 'static> StreamItem<T> for Result<T, E> {
+    fn into_result(self) -> Result<T, CapturedError> {
+        self.map_err(CapturedError::from)
+    }
+}
+
+/// Give the items of `stream` to `field` until it ends or gives an error, and keep `state` up to
+/// date.
+///
+/// This is used by the stream fields, in a resource that restarts it when the signals it reads
+/// change.
+#[doc(hidden)]
+pub async fn collect_stream<T, I, F, S, Fut>(
+    mut field: Signal<F>,
+    mut state: Signal<StreamState>,
+    stream: Fut,
+) where
+    I: StreamItem<T>,
+    F: StreamTarget<T> + 'static,
+    S: Stream<Item = I>,
+    Fut: Future<Output = S>,
+{
+    field.write().reset();
+    state.set(StreamState::Running);
+
+    let mut stream = pin!(stream.await);
+    while let Some(item) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+        match item.into_result() {
+            Ok(item) => field.write().receive(item),
+            Err(error) => {
+                state.set(StreamState::Failed(error));
+                return;
+            },
+        }
+    }
+
+    state.set(StreamState::Done);
+}
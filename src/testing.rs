@@ -0,0 +1,130 @@
+use {
+    crate::Timer,
+    dioxus::{
+        dioxus_core::VirtualDom,
+        prelude::{Element, ScopeId, VNode},
+    },
+    std::{
+        cell::{Cell, RefCell},
+        future::{poll_fn, Future},
+        pin::pin,
+        rc::Rc,
+        task::{Context, Poll, Waker},
+        time::Duration,
+    },
+};
+
+thread_local! {
+    static NOW: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+    static DEADLINES: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+    static SLEPT: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+    static WAKERS: RefCell<Vec<Waker>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A timer with a clock of its own, that only moves when [`block_on`] has nothing else to do.
+pub struct MockTimer;
+
+impl MockTimer {
+    /// The durations of every sleep, in the order they started.
+    pub fn slept() -> Vec<Duration> {
+        SLEPT.with_borrow(Clone::clone)
+    }
+
+    /// Move the clock forward by `duration`, and wake the tasks that are sleeping.
+    pub fn advance(duration: Duration) {
+        NOW.set(NOW.get().saturating_add(duration));
+        WAKERS.take().into_iter().for_each(Waker::wake);
+    }
+}
+
+impl Timer for MockTimer {
+    fn sleep(duration: Duration) -> impl Future<Output = ()> {
+        let deadline = NOW.get().saturating_add(duration);
+        SLEPT.with_borrow_mut(|slept| slept.push(duration));
+        DEADLINES.with_borrow_mut(|deadlines| deadlines.push(deadline));
+
+        poll_fn(move |cx| {
+            if NOW.get() >= deadline {
+                Poll::Ready(())
+            } else {
+                WAKERS.with_borrow_mut(|wakers| wakers.push(cx.waker().clone()));
+                Poll::Pending
+            }
+        })
+    }
+
+    fn now() -> Duration {
+        NOW.get()
+    }
+}
+
+/// Run `future` on the current thread, moving the clock of [`MockTimer`] to the next deadline
+/// whenever it's pending.
+///
+/// ## Panics
+/// If `future` is pending while no sleep is left to wait for.
+pub fn block_on<T>(future: impl Future<Output = T>) -> T {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+            return value;
+        }
+
+        let now = NOW.get();
+        let next = DEADLINES.with_borrow(|deadlines| {
+            deadlines
+                .iter()
+                .copied()
+                .filter(|deadline| *deadline > now)
+                .min()
+        });
+        NOW.set(next.expect("the future is stuck"));
+    }
+}
+
+/// Poll `future` once.
+pub fn poll_once<F: Future + Unpin>(future: &mut F) -> Poll<F::Output> {
+    pin!(future).poll(&mut Context::from_waker(Waker::noop()))
+}
+
+/// A virtual dom with an empty app, to create signals and spawn tasks in.
+pub fn dom() -> VirtualDom {
+    let mut dom = VirtualDom::new(VNode::empty);
+    dom.rebuild_in_place();
+    dom
+}
+
+/// A virtual dom whose app creates a store with `new`, which can call hooks, along with the store.
+pub fn dom_with<S: Copy + 'static>(new: fn() -> S) -> (VirtualDom, S) {
+    #[derive(Clone)]
+    struct App<S> {
+        new: fn() -> S,
+        store: Rc<Cell<Option<S>>>,
+    }
+
+    fn app<S: Copy + 'static>(App { new, store }: App<S>) -> Element {
+        store.set(Some(new()));
+        VNode::empty()
+    }
+
+    let store = Rc::new(Cell::new(None));
+    let mut dom = VirtualDom::new_with_props(
+        app,
+        App {
+            new,
+            store: Rc::clone(&store),
+        },
+    );
+    dom.rebuild_in_place();
+    let store = store
+        .get()
+        .expect("the app creates the store when it's built");
+    (dom, store)
+}
+
+/// Run `f` in the scope of the app of `dom`.
+pub fn in_app<O>(dom: &VirtualDom, f: impl FnOnce() -> O) -> O {
+    dom.in_runtime(|| ScopeId::APP.in_runtime(f))
+}
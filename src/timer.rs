@@ -0,0 +1,283 @@
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::{
+    collections::BTreeMap,
+    pin::Pin,
+    sync::{Condvar, Mutex, MutexGuard, Once, PoisonError},
+    task::{Context, Poll, Waker},
+    thread,
+    time::Instant,
+};
+use std::{future::Future, time::Duration};
+
+/// A way to wait, used by the resources that retry, time out or refresh.
+///
+/// The timer of a resource is given with `#[modx(resource, timer = MyTimer)]`, or
+/// `#[modx::store(timer = MyTimer)]` for every resource of a store. It's [`ThreadTimer`] by
+/// default, except on the web where threads and `Instant` are not available, so a timer must be
+/// given.
+///
+/// A timer that resolves at once can be used to test the resources without waiting.
+///
+/// ## Example
+/// ```ignore
+/// use std::time::Duration;
+///
+/// struct GlooTimer;
+///
+/// impl modx::Timer for GlooTimer {
+///     fn sleep(duration: Duration) -> impl Future<Output = ()> {
+///         gloo_timers::future::sleep(duration)
+///     }
+///
+///     fn now() -> Duration {
+///         Duration::from_secs_f64(js_sys::Date::now() / 1000.)
+///     }
+/// }
+/// ```
+pub trait Timer: 'static {
+    /// Wait for `duration`.
+    fn sleep(duration: Duration) -> impl Future<Output = ()>;
+
+    /// The time elapsed since an arbitrary moment, that is used to know if a cached value is
+    /// stale.
+    fn now() -> Duration;
+}
+
+/// A timer that waits on another thread, which works with any async runtime.
+///
+/// Every sleep is waited by the same thread, which is started by the first one. A sleep that is
+/// dropped before its end is forgotten by the thread.
+///
+/// It doesn't exist on the web, where threads are not available.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub struct ThreadTimer;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl Timer for ThreadTimer {
+    fn sleep(duration: Duration) -> impl Future<Output = ()> {
+        ThreadSleep {
+            deadline: Instant::now().checked_add(duration),
+            id: None,
+        }
+    }
+
+    fn now() -> Duration {
+        thread_local! {
+            static ORIGIN: Instant = Instant::now();
+        }
+
+        ORIGIN.with(Instant::elapsed)
+    }
+}
+
+/// The thread that wakes the sleeps of [`ThreadTimer`].
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+static TIMER_THREAD: TimerThread = TimerThread {
+    sleeps: Mutex::new(Sleeps {
+        wakers: BTreeMap::new(),
+        next_id: 0,
+    }),
+    changed: Condvar::new(),
+    started: Once::new(),
+};
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+struct TimerThread {
+    sleeps: Mutex<Sleeps>,
+    /// Notified when a sleep ends sooner than the ones the thread is waiting for.
+    changed: Condvar,
+    started: Once,
+}
+
+/// The sleeps that aren't over, sorted by deadline.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+struct Sleeps {
+    /// The waker of every sleep, by deadline and then by id, since two sleeps can end at the same
+    /// instant.
+    wakers: BTreeMap<(Instant, u64), Waker>,
+    next_id: u64,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl TimerThread {
+    fn sleeps(&self) -> MutexGuard<'_, Sleeps> {
+        self.sleeps.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wake the sleeps that are over, and wait until the next one is.
+    ///
+    /// The lock is kept from reading the next deadline to waiting, so that a sooner sleep can't be
+    /// added in between without being noticed.
+    fn run(&self) {
+        let mut sleeps = self.sleeps();
+        loop {
+            let now = Instant::now();
+            let mut over = vec![];
+            while let Some(entry) = sleeps.wakers.first_entry() {
+                if entry.key().0 > now {
+                    break;
+                }
+                over.push(entry.remove());
+            }
+
+            if !over.is_empty() {
+                drop(sleeps);
+                over.into_iter().for_each(Waker::wake);
+                sleeps = self.sleeps();
+                continue;
+            }
+
+            sleeps = match sleeps.wakers.first_key_value() {
+                Some(((deadline, _), _)) => {
+                    let timeout = deadline.saturating_duration_since(now);
+                    self.changed
+                        .wait_timeout(sleeps, timeout)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                },
+                None => {
+                    self.changed
+                        .wait(sleeps)
+                        .unwrap_or_else(PoisonError::into_inner)
+                },
+            };
+        }
+    }
+}
+
+/// A sleep of [`ThreadTimer`], which is given to the timer thread when it's first polled.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+struct ThreadSleep {
+    /// `None` if the sleep never ends.
+    deadline: Option<Instant>,
+    /// The id of the sleep in the timer thread, once it's given to it.
+    id: Option<u64>,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl Future for ThreadSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let Some(deadline) = self.deadline else {
+            return Poll::Pending;
+        };
+        if Instant::now() >= deadline {
+            self.forget();
+            return Poll::Ready(());
+        }
+
+        TIMER_THREAD
+            .started
+            .call_once(|| drop(thread::spawn(|| TIMER_THREAD.run())));
+
+        let mut sleeps = TIMER_THREAD.sleeps();
+        let id = *self.id.get_or_insert_with(|| {
+            sleeps.next_id = sleeps.next_id.wrapping_add(1);
+            sleeps.next_id
+        });
+        let is_next = sleeps
+            .wakers
+            .first_key_value()
+            .is_none_or(|(next, _)| (deadline, id) <= *next);
+        sleeps.wakers.insert((deadline, id), cx.waker().clone());
+        drop(sleeps);
+
+        if is_next {
+            TIMER_THREAD.changed.notify_one();
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl ThreadSleep {
+    /// Take the sleep back from the timer thread.
+    fn forget(&mut self) {
+        if let (Some(deadline), Some(id)) = (self.deadline, self.id.take()) {
+            TIMER_THREAD.sleeps().wakers.remove(&(deadline, id));
+        }
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl Drop for ThreadSleep {
+    fn drop(&mut self) {
+        self.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::testing::poll_once,
+        std::{pin::pin, sync::Arc, task::Wake, thread::Thread},
+    };
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Wait for `future` on the current thread, parking it until it's woken.
+    fn wait(future: impl Future<Output = ()>) {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        while future
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending()
+        {
+            thread::park();
+        }
+    }
+
+    fn is_waited(sleep: &ThreadSleep) -> bool {
+        let (Some(deadline), Some(id)) = (sleep.deadline, sleep.id) else {
+            return false;
+        };
+        TIMER_THREAD.sleeps().wakers.contains_key(&(deadline, id))
+    }
+
+    #[test]
+    fn a_sleep_lasts_its_duration() {
+        let start = Instant::now();
+        wait(ThreadTimer::sleep(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn a_sooner_sleep_doesnt_wait_for_a_later_one() {
+        let mut later = ThreadSleep {
+            deadline: Instant::now().checked_add(Duration::from_secs(60)),
+            id: None,
+        };
+        assert!(poll_once(&mut later).is_pending());
+
+        let start = Instant::now();
+        wait(ThreadTimer::sleep(Duration::from_millis(20)));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(is_waited(&later));
+    }
+
+    #[test]
+    fn a_dropped_sleep_is_forgotten() {
+        let mut sleep = ThreadSleep {
+            deadline: Instant::now().checked_add(Duration::from_secs(60)),
+            id: None,
+        };
+        assert!(poll_once(&mut sleep).is_pending());
+        let key = (sleep.deadline, sleep.id);
+        assert!(is_waited(&sleep));
+
+        drop(sleep);
+        let (Some(deadline), Some(id)) = key else {
+            unreachable!("the sleep was given to the timer thread");
+        };
+        assert!(!TIMER_THREAD.sleeps().wakers.contains_key(&(deadline, id)));
+    }
+}